@@ -0,0 +1,658 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use symbolica::{
+    atom::{Atom, AtomView, FunctionBuilder, Symbol},
+    evaluate::{ExpressionEvaluator, FunctionMap, OptimizationSettings},
+    symb,
+};
+
+use super::settings::{InputMode, OdeCoordinate, OdeSettings};
+
+/// Points at which expressions are evaluated to test whether they vanish identically.
+const TEST_POINTS: [(f64, f64); 5] = [
+    (0.37, 0.61),
+    (1.31, -0.72),
+    (-0.93, 2.13),
+    (2.41, 0.45),
+    (0.52, 1.77),
+];
+
+/// The maximum number of Newton iterations used to fit constants and solve implicit solutions.
+const MAX_NEWTON_ITER: usize = 50;
+
+/// The classes of scalar first order ODEs that can be solved in closed form.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OdeClass {
+    /// y' = p(x) y + q(x)
+    Linear,
+    /// y' = p(x) y + q(x) yⁿ
+    Bernoulli,
+    /// y' = g(x) h(y)
+    Separable,
+    /// M(x, y) + N(x, y) y' = 0 with ∂M/∂y = ∂N/∂x
+    Exact,
+}
+
+impl Display for OdeClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OdeClass::Linear => write!(f, "Linear"),
+            OdeClass::Bernoulli => write!(f, "Bernoulli"),
+            OdeClass::Separable => write!(f, "Separable"),
+            OdeClass::Exact => write!(f, "Exact"),
+        }
+    }
+}
+
+/// The form of a closed-form solution with its integration constant C.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolutionForm {
+    /// y = E(x, C)
+    Explicit(Atom),
+    /// F(x, y) = C
+    Implicit(Atom),
+}
+
+/// The general solution of a scalar ODE, before fitting the constant to an initial condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneralSolution {
+    pub class: OdeClass,
+    pub form: SolutionForm,
+    x: Symbol,
+    y: Symbol,
+    c: Symbol,
+}
+
+impl GeneralSolution {
+    /// Tries to solve the scalar ODE of `settings` in closed form.
+    ///
+    /// Returns `None` if the equation is not in a recognized class, or if an
+    /// integral needed for its solution is not in the table of known integrals.
+    pub fn find(settings: &OdeSettings) -> Result<Option<Self>> {
+        if settings.inputs.mode != InputMode::Scalar
+            || settings.coordinate != OdeCoordinate::Cartesian
+        {
+            anyhow::bail!("Closed-form solutions need a scalar ODE in Cartesian coordinates");
+        }
+
+        let f = settings
+            .inputs
+            .parsed_expressions
+            .as_ref()
+            .map_err(|e| anyhow::anyhow!("Failed to parse expressions: {}", e))?
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No ODE given"))?;
+
+        let solver = Solver {
+            x: settings.symbol("x"),
+            y: settings.symbol("y"),
+            c: symb!("C"),
+        };
+
+        // The classes are tested on f as written, as expanding it would split the
+        // products and quotients that separable and exact equations are recognized by.
+        let solution = solver
+            .linear(f)
+            .or_else(|| solver.bernoulli(f))
+            .or_else(|| solver.separable(f))
+            .or_else(|| solver.exact(f));
+
+        Ok(solution.map(|(class, form)| Self {
+            class,
+            form,
+            x: solver.x,
+            y: solver.y,
+            c: solver.c,
+        }))
+    }
+
+    /// Fits the constant of the solution to the initial condition y(x0) = y0.
+    pub fn fit(&self, x0: f64, y0: f64) -> Result<ClosedForm> {
+        let (x, y, c) = (
+            Atom::new_var(self.x),
+            Atom::new_var(self.y),
+            Atom::new_var(self.c),
+        );
+
+        match &self.form {
+            SolutionForm::Explicit(e) => {
+                let mut value = evaluator(e, &[x.clone(), c.clone()])?;
+                let mut slope = evaluator(&e.derivative(self.c), &[x, c])?;
+
+                let constant = newton(0.0, |c| {
+                    let residual = value.evaluate_single(&[x0, c]) - y0;
+                    (residual, slope.evaluate_single(&[x0, c]))
+                })
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Failed to fit the constant to y({}) = {}", x0, y0)
+                })?;
+
+                Ok(ClosedForm {
+                    general: self.clone(),
+                    constant,
+                    value,
+                    slope: None,
+                })
+            }
+            SolutionForm::Implicit(f) => {
+                let mut value = evaluator(f, &[x.clone(), y.clone()])?;
+                let slope = evaluator(&f.derivative(self.y), &[x, y])?;
+
+                let constant = value.evaluate_single(&[x0, y0]);
+                if !constant.is_finite() {
+                    anyhow::bail!("The solution is not defined at ({}, {})", x0, y0);
+                }
+
+                Ok(ClosedForm {
+                    general: self.clone(),
+                    constant,
+                    value,
+                    slope: Some(slope),
+                })
+            }
+        }
+    }
+}
+
+/// A closed-form solution with its constant fitted to an initial condition.
+#[derive(Debug, Clone)]
+pub struct ClosedForm {
+    pub general: GeneralSolution,
+    pub constant: f64,
+    /// Evaluates E(x, C) for explicit solutions or F(x, y) for implicit ones
+    value: ExpressionEvaluator<f64>,
+    /// Evaluates ∂F/∂y for implicit solutions
+    slope: Option<ExpressionEvaluator<f64>>,
+}
+
+impl ClosedForm {
+    /// Evaluates the solution at `x`.
+    ///
+    /// Implicit solutions are solved for y by Newton's method starting from `guess`,
+    /// typically the value of the numerical solution.
+    pub fn eval(&mut self, x: f64, guess: f64) -> Option<f64> {
+        let c = self.constant;
+
+        let y = match &mut self.slope {
+            None => self.value.evaluate_single(&[x, c]),
+            Some(slope) => {
+                let value = &mut self.value;
+                newton(guess, |y| {
+                    let residual = value.evaluate_single(&[x, y]) - c;
+                    (residual, slope.evaluate_single(&[x, y]))
+                })?
+            }
+        };
+
+        y.is_finite().then_some(y)
+    }
+}
+
+/// Solves `g(v) = 0` by Newton's method, where `g` returns the residual and its slope.
+fn newton(guess: f64, mut g: impl FnMut(f64) -> (f64, f64)) -> Option<f64> {
+    let mut v = guess;
+
+    for _ in 0..MAX_NEWTON_ITER {
+        let (residual, slope) = g(v);
+        if !residual.is_finite() || !slope.is_finite() || slope == 0.0 {
+            return None;
+        }
+
+        let step = residual / slope;
+        v -= step;
+
+        if step.abs() <= 1e-12 * (1.0 + v.abs()) {
+            return Some(v);
+        }
+    }
+
+    None
+}
+
+fn evaluator(expr: &Atom, params: &[Atom]) -> Result<ExpressionEvaluator<f64>> {
+    Ok(expr
+        .as_view()
+        .evaluator(&FunctionMap::new(), params, OptimizationSettings::default())
+        .map_err(|e| anyhow::anyhow!("Failed to create evaluator: {:?}", e))?
+        .map_coeff(&|x| x.into()))
+}
+
+fn fun(name: &str, arg: &Atom) -> Atom {
+    FunctionBuilder::new(symb!(name)).add_arg(arg).finish()
+}
+
+/// The rational number with a small denominator closest to `v`, if `v` is one.
+fn rational(v: f64) -> Option<Atom> {
+    (1..=12).find_map(|den| {
+        let num = (v * den as f64).round();
+        ((v * den as f64 - num).abs() < 1e-9)
+            .then(|| &Atom::new_num(num as i64) / &Atom::new_num(den as i64))
+    })
+}
+
+/// Classifies and solves scalar ODEs y' = f(x, y).
+struct Solver {
+    x: Symbol,
+    y: Symbol,
+    c: Symbol,
+}
+
+impl Solver {
+    /// Evaluates `expr` at the test points, skipping the points where it is not defined.
+    fn sample(&self, expr: &Atom) -> Vec<f64> {
+        let params = [Atom::new_var(self.x), Atom::new_var(self.y)];
+        let Ok(mut evaluator) = evaluator(expr, &params) else {
+            return vec![];
+        };
+
+        TEST_POINTS
+            .iter()
+            .map(|&(x, y)| evaluator.evaluate_single(&[x, y]))
+            .filter(|v| v.is_finite())
+            .collect()
+    }
+
+    /// Whether `expr` vanishes identically, tested numerically.
+    fn is_zero(&self, expr: &Atom) -> bool {
+        let values = self.sample(expr);
+        values.len() >= 2 && values.iter().all(|v| v.abs() < 1e-9)
+    }
+
+    fn depends_on(&self, expr: &Atom, s: Symbol) -> bool {
+        !self.is_zero(&expr.derivative(s))
+    }
+
+    /// The value of `expr` if it is a constant.
+    fn constant(&self, expr: &Atom) -> Option<f64> {
+        let values = self.sample(expr);
+        let first = *values.first()?;
+
+        values
+            .iter()
+            .all(|v| (v - first).abs() < 1e-9 * (1.0 + first.abs()))
+            .then_some(first)
+    }
+
+    /// The slope of `u` if it is linear and non-constant in `s`.
+    fn linear_slope(&self, u: &Atom, s: Symbol) -> Option<Atom> {
+        let a = u.derivative(s);
+        (!self.depends_on(&a, s) && !self.is_zero(&a)).then_some(a)
+    }
+
+    /// Integrates `expr` with respect to `s` using a table of elementary integrals.
+    fn integrate(&self, expr: &Atom, s: Symbol) -> Option<Atom> {
+        let var = Atom::new_var(s);
+
+        if !self.depends_on(expr, s) {
+            return Some(expr * &var);
+        }
+
+        match expr.as_view() {
+            AtomView::Add(add) => add.iter().try_fold(Atom::new_num(0), |acc, term| {
+                Some(&acc + &self.integrate(&term.to_owned(), s)?)
+            }),
+            AtomView::Var(_) => Some(&(&var * &var) / &Atom::new_num(2)),
+            AtomView::Pow(pow) => {
+                let (base, exp) = pow.get_base_exp();
+                self.integrate_power(&base.to_owned(), &exp.to_owned(), s)
+            }
+            AtomView::Fun(f) => {
+                if f.get_nargs() != 1 {
+                    return None;
+                }
+
+                let u = f.iter().next()?.to_owned();
+                self.integrate_function(f.get_symbol(), &u, s)
+            }
+            AtomView::Mul(mul) => {
+                let (dependent, constant): (Vec<_>, Vec<_>) = mul
+                    .iter()
+                    .map(|factor| factor.to_owned())
+                    .partition(|factor| self.depends_on(factor, s));
+
+                let constant = constant
+                    .iter()
+                    .fold(Atom::new_num(1), |acc, factor| &acc * factor);
+
+                let integral = match dependent.as_slice() {
+                    [factor] => self.integrate(factor, s)?,
+                    factors => self.integrate_product(factors, s)?,
+                };
+
+                Some(&constant * &integral)
+            }
+            _ => None,
+        }
+    }
+
+    /// Integrates `base^exp` where either the base or the exponent is linear in `s`.
+    fn integrate_power(&self, base: &Atom, exp: &Atom, s: Symbol) -> Option<Atom> {
+        if !self.depends_on(exp, s) {
+            let a = self.linear_slope(base, s)?;
+            let exp_1 = exp + &Atom::new_num(1);
+
+            if self.is_zero(&exp_1) {
+                return Some(&fun("log", base) / &a);
+            }
+
+            return Some(&base.pow(&exp_1) / &(&exp_1 * &a));
+        }
+
+        if !self.depends_on(base, s) {
+            let a = self.linear_slope(exp, s)?;
+            return Some(&base.pow(exp) / &(&a * &fun("log", base)));
+        }
+
+        None
+    }
+
+    fn integrate_function(&self, f: Symbol, u: &Atom, s: Symbol) -> Option<Atom> {
+        let a = self.linear_slope(u, s)?;
+
+        let integral = if f == symb!("exp") {
+            fun("exp", u)
+        } else if f == symb!("sin") {
+            -fun("cos", u)
+        } else if f == symb!("cos") {
+            fun("sin", u)
+        } else {
+            return None;
+        };
+
+        Some(&integral / &a)
+    }
+
+    /// Integrates products sⁿ exp(a s + b) for natural n by repeated integration by parts.
+    fn integrate_product(&self, factors: &[Atom], s: Symbol) -> Option<Atom> {
+        let is_exp = |factor: &Atom| match factor.as_view() {
+            AtomView::Fun(f) => f.get_symbol() == symb!("exp") && f.get_nargs() == 1,
+            _ => false,
+        };
+
+        let (exps, powers): (Vec<_>, Vec<_>) = factors.iter().partition(|factor| is_exp(factor));
+        let [exponential] = exps.as_slice() else {
+            return None;
+        };
+
+        let u = match exponential.as_view() {
+            AtomView::Fun(f) => f.iter().next()?.to_owned(),
+            _ => return None,
+        };
+        let a = self.linear_slope(&u, s)?;
+
+        let var = Atom::new_var(s);
+        let power = powers
+            .iter()
+            .fold(Atom::new_num(1), |acc, factor| &acc * *factor);
+
+        // The degree of the polynomial factor, s dp/ds / p
+        let n = self.constant(&(&(&var * &power.derivative(s)) / &power))?;
+        if n < 0.0 || n.fract() != 0.0 || !self.is_zero(&(&power - &var.npow(n as i64))) {
+            return None;
+        }
+
+        let n = n as i64;
+        let mut sum = Atom::new_num(0);
+        let mut coefficient = 1i64;
+
+        for k in 0..=n {
+            let term = &(&Atom::new_num(coefficient) * &var.npow(n - k)) / &a.npow(k + 1);
+            sum = &sum + &term;
+            coefficient *= -(n - k);
+        }
+
+        Some(&fun("exp", &u) * &sum)
+    }
+
+    /// The solution of the linear equation v' = p v + q as v = E(x, C).
+    fn solve_linear(&self, p: &Atom, q: &Atom) -> Option<Atom> {
+        let big_p = self.integrate(p, self.x)?;
+        let integrand = (q * &fun("exp", &-big_p.clone())).expand();
+        let integral = self.integrate(&integrand, self.x)?;
+
+        Some(&fun("exp", &big_p) * &(&integral + &Atom::new_var(self.c)))
+    }
+
+    fn linear(&self, f: &Atom) -> Option<(OdeClass, SolutionForm)> {
+        let f = f.expand();
+        let p = f.derivative(self.y);
+        if self.depends_on(&p, self.y) {
+            return None;
+        }
+
+        let q = (&f - &(&p * &Atom::new_var(self.y))).expand();
+        let solution = self.solve_linear(&p, &q)?;
+
+        Some((OdeClass::Linear, SolutionForm::Explicit(solution)))
+    }
+
+    fn bernoulli(&self, f: &Atom) -> Option<(OdeClass, SolutionForm)> {
+        let f = f.expand();
+        let AtomView::Add(add) = f.as_view() else {
+            return None;
+        };
+
+        let y = Atom::new_var(self.y);
+        let mut linear = Atom::new_num(0);
+        let mut nonlinear = Atom::new_num(0);
+        let mut degree = None;
+
+        for term in add.iter() {
+            let term = term.to_owned();
+            let n = self.constant(&(&(&y * &term.derivative(self.y)) / &term))?;
+
+            if (n - 1.0).abs() < 1e-9 {
+                linear = &linear + &term;
+            } else if degree.map_or(true, |d: f64| (d - n).abs() < 1e-9) {
+                degree = Some(n);
+                nonlinear = &nonlinear + &term;
+            } else {
+                return None;
+            }
+        }
+
+        let n = degree.filter(|n| n.abs() > 1e-9)?;
+        let n_atom = rational(n)?;
+        let one_minus_n = &Atom::new_num(1) - &n_atom;
+
+        let p = (&linear / &y).expand();
+        let q = (&nonlinear / &y.pow(&n_atom)).expand();
+
+        if self.depends_on(&p, self.y) || self.depends_on(&q, self.y) {
+            return None;
+        }
+
+        // v = y^(1 - n) satisfies the linear equation v' = (1 - n)(p v + q).
+        let v = self.solve_linear(&(&one_minus_n * &p), &(&one_minus_n * &q))?;
+        let solution = v.pow(&(&Atom::new_num(1) / &one_minus_n));
+
+        Some((OdeClass::Bernoulli, SolutionForm::Explicit(solution)))
+    }
+
+    fn separable(&self, f: &Atom) -> Option<(OdeClass, SolutionForm)> {
+        let factors = match f.as_view() {
+            AtomView::Mul(mul) => mul.iter().map(|factor| factor.to_owned()).collect(),
+            _ => vec![f.clone()],
+        };
+
+        let mut g = Atom::new_num(1);
+        let mut h = Atom::new_num(1);
+
+        for factor in factors {
+            match (
+                self.depends_on(&factor, self.x),
+                self.depends_on(&factor, self.y),
+            ) {
+                (true, true) => return None,
+                (_, false) => g = &g * &factor,
+                (false, true) => h = &h * &factor,
+            }
+        }
+
+        let integral_g = self.integrate(&g.expand(), self.x)?;
+
+        if !self.depends_on(&h, self.y) {
+            let solution = &(&h * &integral_g) + &Atom::new_var(self.c);
+            return Some((OdeClass::Separable, SolutionForm::Explicit(solution)));
+        }
+
+        if let Some(solution) = self.solve_quadratic(&h, &integral_g) {
+            return Some((OdeClass::Separable, SolutionForm::Explicit(solution)));
+        }
+
+        let integral_h = self.integrate(&(&Atom::new_num(1) / &h).expand(), self.y)?;
+
+        Some((
+            OdeClass::Separable,
+            SolutionForm::Implicit(&integral_h - &integral_g),
+        ))
+    }
+
+    /// The solution of y' = g(x) h(y) for a quadratic h = a y² + b y + c without real
+    /// roots, as ∫dy/h = 2/d atan((2a y + b)/d) = ∫g dx + C with d² = 4ac - b².
+    fn solve_quadratic(&self, h: &Atom, integral_g: &Atom) -> Option<Atom> {
+        let y = Atom::new_var(self.y);
+        let h_y = h.derivative(self.y);
+
+        let a = self.constant(&h_y.derivative(self.y))? / 2.0;
+        if a.abs() < 1e-9 {
+            return None;
+        }
+        let a = rational(a)?;
+
+        let b = self.constant(&(&h_y - &(&(&Atom::new_num(2) * &a) * &y)))?;
+        let b = rational(b)?;
+
+        let c = self.constant(&(&(h - &(&a * &y.npow(2))) - &(&b * &y)))?;
+        let c = rational(c)?;
+
+        let discriminant = &(&(&Atom::new_num(4) * &a) * &c) - &b.npow(2);
+        if self.constant(&discriminant)? <= 1e-9 {
+            return None;
+        }
+
+        let d = discriminant.pow(&(&Atom::new_num(1) / &Atom::new_num(2)));
+        let phase = &(&d * &(integral_g + &Atom::new_var(self.c))) / &Atom::new_num(2);
+        let tan = &fun("sin", &phase) / &fun("cos", &phase);
+
+        Some(&(&(&d * &tan) - &b) / &(&Atom::new_num(2) * &a))
+    }
+
+    fn exact(&self, f: &Atom) -> Option<(OdeClass, SolutionForm)> {
+        // Write y' = f as M + N y' = 0 with f = -M/N.
+        let factors = match f.as_view() {
+            AtomView::Mul(mul) => mul.iter().map(|factor| factor.to_owned()).collect(),
+            _ => vec![f.clone()],
+        };
+
+        let mut numerator = Atom::new_num(1);
+        let mut denominator = Atom::new_num(1);
+
+        for factor in factors {
+            match factor.as_view() {
+                AtomView::Pow(pow) => {
+                    let (base, exp) = pow.get_base_exp();
+                    let (base, exp) = (base.to_owned(), exp.to_owned());
+
+                    match self.constant(&exp) {
+                        Some(e) if e < 0.0 => denominator = &denominator * &base.pow(&-exp.clone()),
+                        _ => numerator = &numerator * &factor,
+                    }
+                }
+                _ => numerator = &numerator * &factor,
+            }
+        }
+
+        let m = -numerator;
+        let n = denominator;
+
+        if !self.is_zero(&(&m.derivative(self.y) - &n.derivative(self.x))) {
+            return None;
+        }
+
+        // F = ∫M dx + ∫(N - ∂/∂y ∫M dx) dy
+        let integral_m = self.integrate(&m.expand(), self.x)?;
+        let remainder = (&n - &integral_m.derivative(self.y)).expand();
+        let integral_rest = self.integrate(&remainder, self.y)?;
+
+        Some((
+            OdeClass::Exact,
+            SolutionForm::Implicit(&integral_m + &integral_rest),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solver() -> Solver {
+        Solver {
+            x: symb!("x"),
+            y: symb!("y"),
+            c: symb!("C"),
+        }
+    }
+
+    fn parse(input: &str) -> Atom {
+        Atom::parse(input).unwrap()
+    }
+
+    /// The class `find` assigns to y' = `input`.
+    fn classify(input: &str) -> Option<OdeClass> {
+        let mut settings = OdeSettings::default();
+        settings.inputs.inputs = vec![input.to_string()];
+        settings.update_inputs();
+
+        GeneralSolution::find(&settings)
+            .unwrap()
+            .map(|solution| solution.class)
+    }
+
+    #[test]
+    fn linear() {
+        assert!(solver().linear(&parse("y + x")).is_some());
+        assert!(solver().linear(&parse("y^2 + x")).is_none());
+        assert_eq!(classify("y + x"), Some(OdeClass::Linear));
+    }
+
+    #[test]
+    fn bernoulli() {
+        assert!(solver().bernoulli(&parse("y + x*y^2")).is_some());
+        assert!(solver().bernoulli(&parse("y + y^2 + x")).is_none());
+        assert_eq!(classify("y + x*y^2"), Some(OdeClass::Bernoulli));
+    }
+
+    #[test]
+    fn separable() {
+        assert!(solver().separable(&parse("(1+x^2)*(1+y^2)")).is_some());
+        assert!(solver().separable(&parse("sin(x*y)")).is_none());
+        assert_eq!(classify("(1+x^2)*(1+y^2)"), Some(OdeClass::Separable));
+        assert_eq!(classify("cos(x)*(1+y^2)"), Some(OdeClass::Separable));
+    }
+
+    #[test]
+    fn exact() {
+        assert!(solver().exact(&parse("-(2*x+y)/(x+2*y)")).is_some());
+        assert!(solver().exact(&parse("-(2*x+y)/(3*x+2*y)")).is_none());
+        assert_eq!(classify("-(2*x+y)/(x+2*y)"), Some(OdeClass::Exact));
+    }
+
+    #[test]
+    fn separable_quadratic_solution() {
+        // y' = (1 + x²)(1 + y²) with y(0) = 0 is solved by y = tan(x + x³/3).
+        let mut settings = OdeSettings::default();
+        settings.inputs.inputs = vec!["(1+x^2)*(1+y^2)".to_string()];
+        settings.update_inputs();
+
+        let general = GeneralSolution::find(&settings).unwrap().unwrap();
+        let mut solution = general.fit(0.0, 0.0).unwrap();
+
+        for x in [-0.5, 0.2, 0.6] {
+            let expected = (x + x * x * x / 3.0f64).tan();
+            let y = solution.eval(x, 0.0).unwrap();
+            assert!((y - expected).abs() < 1e-9, "y({}) = {}", x, y);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use peroxide::fuga::{ODEError, DP45};
+use tracing::{debug, warn};
+
+use super::{
+    closed_form::ClosedForm,
+    parameters::{MaxStepSize, MinStepSize, SolverParameters, Tolerance},
+    schemes::{ImplicitMethod, OdeSolver},
+    settings::OdeSettings,
+    solution::Solution,
+    solver::{solve_ode, ExpressionODEProblem},
+    stepper::{Stepper, Tableau},
+};
+
+/// The step sizes a convergence study runs each solver at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvergenceSettings {
+    /// The number of steps over the integration length at the coarsest step size
+    pub coarsest_steps: usize,
+    /// The number of times the step size is halved
+    pub refinements: usize,
+    /// The tolerance of the numerical reference solution
+    pub reference_tolerance: f64,
+}
+
+impl Default for ConvergenceSettings {
+    fn default() -> Self {
+        Self {
+            coarsest_steps: 8,
+            refinements: 6,
+            reference_tolerance: 1e-12,
+        }
+    }
+}
+
+impl ConvergenceSettings {
+    /// The step sizes of the study over an integration of length `length`, coarsest first.
+    pub fn step_sizes(&self, length: f64) -> Vec<f64> {
+        (0..=self.refinements)
+            .map(|k| length / (self.coarsest_steps << k) as f64)
+            .collect()
+    }
+}
+
+/// The solution the global error of each run is measured against.
+pub enum Reference {
+    /// A solution computed at a tight tolerance, on the grid of the finest step size
+    Numerical {
+        t_start: f64,
+        dt: f64,
+        /// The states at `t_start + i dt`, up to where the integration stopped
+        states: Vec<Vec<f64>>,
+    },
+    /// The exact solution of a scalar ODE
+    ClosedForm(ClosedForm),
+}
+
+impl Reference {
+    /// Solves the ODE of `settings` with DP45 at the tolerance of the study.
+    ///
+    /// Steps are cut to land exactly on the grid of the finest step size, which
+    /// contains the grids of every run, so that the runs are compared against
+    /// states of the integration itself rather than an interpolation of it.
+    pub fn numerical(settings: &OdeSettings, convergence: &ConvergenceSettings) -> Result<Self> {
+        let settings = study_settings(settings);
+        let problem = ExpressionODEProblem::create(&settings)?;
+
+        let steps = convergence.coarsest_steps << convergence.refinements;
+        let dt = settings.integration_length / steps as f64;
+        let stepper = Tableau(DP45::new(
+            convergence.reference_tolerance,
+            0.9,
+            1e-12,
+            dt,
+            settings.parameters.max_steps.0.max(10_000),
+        ));
+
+        let (t_span, mut y) = settings.initial_value_problem();
+        let mut states = vec![y.clone()];
+        let mut h = dt;
+
+        'grid: for i in 0..steps {
+            let mut t = t_span.0 + i as f64 * dt;
+            let mut remaining = dt;
+
+            while remaining > 0.0 {
+                let step = match stepper.step(&problem, t, &mut y, h.min(remaining)) {
+                    Ok(step) => step,
+                    Err(e) if e.downcast_ref::<ODEError>().is_some() => break 'grid,
+                    Err(e) => return Err(e),
+                };
+
+                t += step.dt;
+                remaining -= step.dt;
+                h = step.dt_next;
+            }
+
+            if !y.iter().all(|y| y.is_finite()) {
+                break;
+            }
+            states.push(y.clone());
+        }
+
+        Ok(Self::Numerical {
+            t_start: t_span.0,
+            dt,
+            states,
+        })
+    }
+
+    /// The reference state at `t`, given the state `y` of the run being compared.
+    fn eval(&mut self, t: f64, y: &[f64]) -> Option<Vec<f64>> {
+        match self {
+            Reference::Numerical {
+                t_start,
+                dt,
+                states,
+            } => {
+                // Points off the grid, such as those of halved GL4 steps, are skipped.
+                let i = ((t - *t_start) / *dt).round();
+                let on_grid = i >= 0.0 && (t - (*t_start + i * *dt)).abs() <= 1e-9 * *dt;
+                on_grid.then(|| states.get(i as usize).cloned()).flatten()
+            }
+            Reference::ClosedForm(closed_form) => Some(vec![closed_form.eval(t, y[0])?]),
+        }
+    }
+}
+
+/// The global errors of one solver over the step sizes of a study.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvergenceSeries {
+    pub solver: OdeSolver,
+    pub step_sizes: Vec<f64>,
+    /// The maximum norm of the error over every step, for each step size
+    pub errors: Vec<f64>,
+    /// The slope of the least squares fit of log error against log step size
+    pub observed_order: Option<f64>,
+}
+
+/// The outcome of running every applicable solver over a range of fixed step sizes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvergenceStudy {
+    pub series: Vec<ConvergenceSeries>,
+    /// Whether the errors were measured against a closed-form solution
+    pub exact_reference: bool,
+}
+
+impl ConvergenceStudy {
+    /// Runs each solver on the problem of `settings` at the step sizes of `convergence`.
+    ///
+    /// Adaptive methods are run with their step size pinned, so that every method
+    /// takes the same steps. Solvers that cannot solve the problem are skipped.
+    pub fn run(
+        settings: &OdeSettings,
+        convergence: &ConvergenceSettings,
+        mut reference: Reference,
+    ) -> Result<Self> {
+        let exact_reference = matches!(reference, Reference::ClosedForm(_));
+        let step_sizes = convergence.step_sizes(settings.integration_length);
+        let (t_span, ics) = settings.initial_value_problem();
+
+        let mut series = vec![];
+        for solver in OdeSolver::ALL {
+            let errors = step_sizes
+                .iter()
+                .map(|&dt| {
+                    let settings = fixed_step_settings(settings, solver, dt);
+                    let solution = solve_ode(&settings, t_span, &ics, None)?;
+                    Ok(global_error(&solution, t_span.1, &mut reference))
+                })
+                .collect::<Result<Vec<_>>>();
+
+            match errors {
+                Ok(errors) => {
+                    let observed_order = observed_order(&step_sizes, &errors);
+                    debug!(target: "metrics", solver = solver.name(), ?observed_order);
+
+                    series.push(ConvergenceSeries {
+                        solver,
+                        step_sizes: step_sizes.clone(),
+                        errors,
+                        observed_order,
+                    });
+                }
+                Err(e) => warn!("Skipping {} in convergence study: {}", solver.name(), e),
+            }
+        }
+
+        Ok(Self {
+            series,
+            exact_reference,
+        })
+    }
+
+    /// The study as CSV, with one row per solver and step size.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "solver,step_size,global_error,observed_order\n".to_string();
+
+        for series in &self.series {
+            let order = series
+                .observed_order
+                .map(|order| order.to_string())
+                .unwrap_or_default();
+
+            for (dt, error) in series.step_sizes.iter().zip(&series.errors) {
+                let _ = writeln!(csv, "{},{},{},{}", series.solver.name(), dt, error, order);
+            }
+        }
+
+        csv
+    }
+}
+
+/// The settings of the study, without events that could end the integration early.
+fn study_settings(settings: &OdeSettings) -> OdeSettings {
+    let mut settings = settings.clone();
+    settings.events.clear();
+    settings
+}
+
+/// Settings that make `solver` take steps of exactly `dt`.
+fn fixed_step_settings(settings: &OdeSettings, solver: OdeSolver, dt: f64) -> OdeSettings {
+    let mut settings = study_settings(settings);
+    settings.ode_solver = solver;
+    settings.termination.min_step_size = 0.0;
+
+    // An infinite tolerance accepts every step, and the step size bounds then pin
+    // the proposed step size to `dt`. GL4 is already fixed-step, and uses the
+    // tolerance for its Newton iteration instead.
+    let tolerance = match solver {
+        OdeSolver::Implicit(ImplicitMethod::GL4) => 1e-12,
+        _ => f64::INFINITY,
+    };
+
+    settings.parameters = SolverParameters {
+        tolerance: Tolerance(tolerance),
+        min_step_size: MinStepSize(dt),
+        max_step_size: MaxStepSize(dt),
+        initial_step_size: dt,
+        ..settings.parameters
+    };
+
+    settings
+}
+
+/// The largest error of `solution` against the reference over the integration span.
+fn global_error(solution: &Solution, t_end: f64, reference: &mut Reference) -> f64 {
+    solution
+        .points()
+        .filter(|&(t, _)| t <= t_end + 1e-9)
+        .filter_map(|(t, y)| {
+            let exact = reference.eval(t, y)?;
+            Some(
+                y.iter()
+                    .zip(exact)
+                    .map(|(y, exact)| (y - exact).abs())
+                    .fold(0.0, f64::max),
+            )
+        })
+        .fold(0.0, |max, error| {
+            if max.is_nan() || error.is_nan() {
+                f64::NAN
+            } else {
+                max.max(error)
+            }
+        })
+}
+
+/// The slope of the least squares line through (log dt, log error).
+///
+/// Errors at the level of round-off are left out, as they no longer decrease with the step size.
+fn observed_order(step_sizes: &[f64], errors: &[f64]) -> Option<f64> {
+    let points = step_sizes
+        .iter()
+        .zip(errors)
+        .filter(|&(_, &error)| error.is_finite() && error > 1e-13)
+        .map(|(dt, error)| (dt.ln(), error.ln()))
+        .collect::<Vec<_>>();
+
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let covariance = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>();
+    let variance = points
+        .iter()
+        .map(|(x, _)| (x - mean_x).powi(2))
+        .sum::<f64>();
+
+    (variance > 0.0).then(|| covariance / variance)
+}
@@ -0,0 +1,142 @@
+use anyhow::Result;
+use peroxide::fuga::*;
+
+use super::interpolation::hermite;
+
+/// A solution that can be evaluated at any point of its domain.
+///
+/// Between two solver steps the solution is interpolated by a cubic Hermite
+/// polynomial built from the states and derivatives at both ends of the step.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DenseSolution {
+    pub t: Vec<f64>,
+    pub y: Vec<Vec<f64>>,
+    pub dy: Vec<Vec<f64>>,
+}
+
+impl DenseSolution {
+    /// Builds the dense output for the steps `t`, `y` of a solution to `problem`.
+    pub fn new<P: ODEProblem>(problem: &P, t: Vec<f64>, y: Vec<Vec<f64>>) -> Result<Self> {
+        let dy = t
+            .iter()
+            .zip(&y)
+            .map(|(&t, y)| {
+                let mut dy = vec![0.0; y.len()];
+                problem.rhs(t, y, &mut dy)?;
+                Ok(dy)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { t, y, dy })
+    }
+
+    pub fn len(&self) -> usize {
+        self.t.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.t.is_empty()
+    }
+
+    /// The interval of the independent variable covered by the solution.
+    pub fn domain(&self) -> Option<(f64, f64)> {
+        Some((*self.t.first()?, *self.t.last()?))
+    }
+
+    /// Evaluates the solution at `t`, or `None` if `t` lies outside of the domain.
+    pub fn eval(&self, t: f64) -> Option<Vec<f64>> {
+        let (t_start, t_end) = self.domain()?;
+
+        if !(t_start..=t_end).contains(&t) {
+            return None;
+        }
+
+        let i = self.t.partition_point(|&ti| ti <= t);
+        if i >= self.len() {
+            return self.y.last().cloned();
+        }
+
+        let i = i.max(1);
+        Some(hermite(
+            (self.t[i - 1], &self.y[i - 1], &self.dy[i - 1]),
+            (self.t[i], &self.y[i], &self.dy[i]),
+            t,
+        ))
+    }
+
+    /// Samples the solution at `n` uniformly spaced points over `(t_start, t_end)`.
+    ///
+    /// Points outside of the domain of the solution are skipped.
+    pub fn sample(&self, (t_start, t_end): (f64, f64), n: usize) -> (Vec<f64>, Vec<Vec<f64>>) {
+        if n < 2 {
+            return self
+                .eval(t_start)
+                .map(|y| (vec![t_start], vec![y]))
+                .unwrap_or_default();
+        }
+
+        let dt = (t_end - t_start) / (n - 1) as f64;
+
+        // The last point is set exactly, as t_start + (n - 1) dt can round past t_end.
+        (0..n)
+            .map(|i| {
+                if i == n - 1 {
+                    t_end
+                } else {
+                    t_start + i as f64 * dt
+                }
+            })
+            .filter_map(|t| self.eval(t).map(|y| (t, y)))
+            .unzip()
+    }
+
+    /// Samples the solution at `n` uniformly spaced points over its whole domain.
+    pub fn resample(&self, n: usize) -> (Vec<f64>, Vec<Vec<f64>>) {
+        match self.domain() {
+            Some(domain) => self.sample(domain, n),
+            None => Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line y = t over `domain`, with a step at each end.
+    fn line((t_start, t_end): (f64, f64)) -> DenseSolution {
+        DenseSolution {
+            t: vec![t_start, t_end],
+            y: vec![vec![t_start], vec![t_end]],
+            dy: vec![vec![1.0], vec![1.0]],
+        }
+    }
+
+    #[test]
+    fn sample_includes_both_ends() {
+        // Spans where t_start + (n - 1) dt rounds past t_end.
+        for (domain, n) in [((-1.7, 0.1), 19), ((-1.9, 1.6), 51), ((-4.7, 2.1), 85)] {
+            let (t, y) = line(domain).sample(domain, n);
+
+            assert_eq!(t.len(), n);
+            assert_eq!(t[0], domain.0);
+            assert_eq!(t[n - 1], domain.1);
+            assert!((y[n - 1][0] - domain.1).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn interpolates_between_steps() {
+        let dense = DenseSolution {
+            t: vec![0.0, 1.0],
+            y: vec![vec![0.0], vec![1.0]],
+            dy: vec![vec![0.0], vec![2.0]],
+        };
+
+        // The Hermite interpolant reproduces y = t² exactly.
+        for t in [0.25, 0.5, 0.75] {
+            assert!((dense.eval(t).unwrap()[0] - t * t).abs() < 1e-12);
+        }
+        assert_eq!(dense.eval(1.5), None);
+    }
+}
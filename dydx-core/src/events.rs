@@ -0,0 +1,302 @@
+use anyhow::Result;
+use peroxide::fuga::*;
+use serde::{Deserialize, Serialize};
+use symbolica::{
+    atom::Atom,
+    evaluate::{ExpressionEvaluator, FunctionMap, OptimizationSettings},
+};
+
+use super::{
+    interpolation::hermite,
+    settings::{unparsed, OdeSettings},
+};
+
+/// The maximum number of bisection steps used to locate an event within a step.
+const MAX_LOCATE_ITER: usize = 100;
+
+/// Which zero crossings of an event function are reported.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum EventDirection {
+    /// Crossings in either direction
+    Both,
+    /// Crossings where g(t, y) goes from negative to positive
+    Rising,
+    /// Crossings where g(t, y) goes from positive to negative
+    Falling,
+}
+
+impl EventDirection {
+    fn accepts(&self, rising: bool) -> bool {
+        match self {
+            EventDirection::Both => true,
+            EventDirection::Rising => rising,
+            EventDirection::Falling => !rising,
+        }
+    }
+}
+
+/// A user-specified event function g(t, y) = 0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventSpec {
+    pub input: String,
+    #[serde(skip, default = "unparsed")]
+    pub parsed_expression: Result<Atom, String>,
+    pub direction: EventDirection,
+    /// Stop the integration at the first accepted crossing.
+    pub terminal: bool,
+}
+
+impl EventSpec {
+    pub fn new(input: &str) -> Self {
+        let mut spec = Self {
+            input: input.to_string(),
+            parsed_expression: Err(String::new()),
+            direction: EventDirection::Both,
+            terminal: false,
+        };
+
+        spec.parse_expression();
+        spec
+    }
+
+    pub fn parse_expression(&mut self) {
+        self.parsed_expression = Atom::parse(&self.input).map_err(|e| e.to_string());
+    }
+}
+
+/// An event located during the integration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventHit {
+    /// Index of the event in `OdeSettings::events`
+    pub event: usize,
+    pub t: f64,
+    pub y: Vec<f64>,
+    /// Whether g(t, y) was increasing through zero
+    pub rising: bool,
+    pub terminal: bool,
+}
+
+/// The compiled event functions of an ODE, evaluated alongside the solver.
+pub(crate) struct EventFunctions {
+    specs: Vec<(EventDirection, bool)>,
+    evaluator: ExpressionEvaluator<f64>,
+}
+
+impl EventFunctions {
+    /// Compiles the events in `settings`, or returns `None` if there are none.
+    pub fn create(settings: &OdeSettings) -> Result<Option<Self>> {
+        if settings.events.is_empty() {
+            return Ok(None);
+        }
+
+        let expressions = settings
+            .events
+            .iter()
+            .map(|event| {
+                event
+                    .parsed_expression
+                    .clone()
+                    .map_err(|e| anyhow::anyhow!("Failed to parse event '{}': {}", event.input, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let expressions = expressions
+            .iter()
+            .map(|expr| expr.as_view())
+            .collect::<Vec<_>>();
+
+        let evaluator = Atom::evaluator_multiple(
+            expressions.as_slice(),
+            &FunctionMap::new(),
+            settings.variables().as_slice(),
+            OptimizationSettings::default(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to create event evaluator: {:?}", e))?
+        .map_coeff(&|x| x.into());
+
+        let specs = settings
+            .events
+            .iter()
+            .map(|event| (event.direction, event.terminal))
+            .collect();
+
+        Ok(Some(Self { specs, evaluator }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.specs.len()
+    }
+
+    pub fn evaluate(&self, t: f64, y: &[f64]) -> Vec<f64> {
+        let in_ = std::iter::once(&t).chain(y).copied().collect::<Vec<_>>();
+        let mut out = vec![0.0; self.len()];
+
+        let evaluator = &mut self.evaluator.clone();
+        evaluator.evaluate(in_.as_slice(), &mut out);
+
+        out
+    }
+
+    /// Finds the events that occur within the step from `(t0, y0)` to `(t1, y1)`.
+    ///
+    /// The crossings are located by bisection on the Hermite interpolant of the
+    /// step and are returned in order of occurrence. If a terminal event is hit,
+    /// it is the last event returned.
+    ///
+    /// A step ending exactly on g = 0 reports the event at its end, including a
+    /// tangent touch, and the step starting there reports nothing, so that each
+    /// zero is found once.
+    pub fn locate<P: ODEProblem>(
+        &self,
+        problem: &P,
+        (t0, y0, g0): (f64, &[f64], &[f64]),
+        (t1, y1, g1): (f64, &[f64], &[f64]),
+    ) -> Result<Vec<EventHit>> {
+        let crossings = (0..self.len())
+            .filter(|&i| g0[i] != 0.0 && (g1[i] == 0.0 || g0[i].signum() != g1[i].signum()))
+            .filter(|&i| g1[i].is_finite() && self.specs[i].0.accepts(g0[i] < 0.0))
+            .collect::<Vec<_>>();
+
+        if crossings.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut f0 = vec![0.0; y0.len()];
+        let mut f1 = vec![0.0; y1.len()];
+        problem.rhs(t0, y0, &mut f0)?;
+        problem.rhs(t1, y1, &mut f1)?;
+
+        let interpolate = |t: f64| hermite((t0, y0, &f0), (t1, y1, &f1), t);
+
+        let mut hits = crossings
+            .into_iter()
+            .map(|i| {
+                let (mut a, mut b) = (t0, t1);
+                let mut g_a = g0[i];
+
+                // A zero at the end of the step needs no bisection.
+                let iterations = if g1[i] == 0.0 { 0 } else { MAX_LOCATE_ITER };
+
+                for _ in 0..iterations {
+                    let mid = 0.5 * (a + b);
+                    if mid <= a || mid >= b {
+                        break;
+                    }
+
+                    let g_mid = self.evaluate(mid, &interpolate(mid))[i];
+                    if g_mid == 0.0 {
+                        b = mid;
+                        break;
+                    }
+
+                    if g_mid.signum() == g_a.signum() {
+                        (a, g_a) = (mid, g_mid);
+                    } else {
+                        b = mid;
+                    }
+                }
+
+                EventHit {
+                    event: i,
+                    t: b,
+                    y: interpolate(b),
+                    rising: g0[i] < 0.0,
+                    terminal: self.specs[i].1,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        hits.sort_by(|a, b| a.t.total_cmp(&b.t));
+
+        if let Some(terminal) = hits.iter().position(|hit| hit.terminal) {
+            hits.truncate(terminal + 1);
+        }
+
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{settings::OdeInputs, solver::ExpressionODEProblem};
+
+    /// The ODE x' = 1 with the given events, so that x = t along the solution.
+    fn clock(events: &[(&str, EventDirection)]) -> (ExpressionODEProblem, EventFunctions) {
+        let mut settings = OdeSettings::default();
+        settings.inputs = OdeInputs::system(&["x"], &["1"]);
+        settings.update_inputs();
+        settings.events = events
+            .iter()
+            .map(|&(input, direction)| EventSpec {
+                direction,
+                ..EventSpec::new(input)
+            })
+            .collect();
+
+        let problem = ExpressionODEProblem::create(&settings).unwrap();
+        let events = EventFunctions::create(&settings).unwrap().unwrap();
+        (problem, events)
+    }
+
+    /// Locates the events over consecutive steps between the states x = t in `steps`.
+    fn hits(events: &[(&str, EventDirection)], steps: &[f64]) -> Vec<EventHit> {
+        let (problem, functions) = clock(events);
+
+        steps
+            .windows(2)
+            .flat_map(|step| {
+                let (t0, t1) = (step[0], step[1]);
+                let (g0, g1) = (functions.evaluate(t0, &[t0]), functions.evaluate(t1, &[t1]));
+                functions
+                    .locate(&problem, (t0, &[t0], &g0), (t1, &[t1], &g1))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn locates_crossing_within_step() {
+        let hits = hits(&[("x - 0.3", EventDirection::Both)], &[0.0, 0.5, 1.0]);
+
+        assert_eq!(hits.len(), 1);
+        assert!((hits[0].t - 0.3).abs() < 1e-9);
+        assert!(hits[0].rising);
+    }
+
+    #[test]
+    fn falling_crossing_exactly_at_step_end() {
+        let hits = hits(&[("0.5 - x", EventDirection::Falling)], &[0.0, 0.5, 1.0]);
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].t, 0.5);
+        assert!(!hits[0].rising);
+    }
+
+    #[test]
+    fn rising_crossing_exactly_at_step_end() {
+        let hits = hits(&[("x - 0.5", EventDirection::Rising)], &[0.0, 0.5, 1.0]);
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].t, 0.5);
+        assert!(hits[0].rising);
+    }
+
+    #[test]
+    fn tangent_between_steps_is_not_a_crossing() {
+        let hits = hits(
+            &[("(x - 0.5)^2", EventDirection::Both)],
+            &[0.0, 0.4, 0.7, 1.0],
+        );
+
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn tangent_touch_at_step_end_is_reported_once() {
+        let hits = hits(&[("(x - 0.5)^2", EventDirection::Both)], &[0.0, 0.5, 1.0]);
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].t, 0.5);
+    }
+}
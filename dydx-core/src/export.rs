@@ -0,0 +1,307 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{
+    hamiltonian::energy,
+    schemes::OdeSolver,
+    settings::{InputMode, OdeSettings},
+    solution::{Solution, SolverStats},
+};
+
+/// The file formats solutions can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Csv,
+    Json,
+    /// NumPy's `.npy`, holding the rows of a single solution as a float64 matrix
+    Npy,
+}
+
+impl DataFormat {
+    pub const ALL: [DataFormat; 3] = [DataFormat::Csv, DataFormat::Json, DataFormat::Npy];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DataFormat::Csv => "csv",
+            DataFormat::Json => "json",
+            DataFormat::Npy => "npy",
+        }
+    }
+
+    /// The format with the extension of `path`, if there is one.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension() == extension)
+    }
+}
+
+/// Which derived quantities are exported next to the state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportSettings {
+    /// The derivative of each component of the state
+    pub derivatives: bool,
+    /// The value of the Hamiltonian, for Hamiltonian systems
+    pub energy: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            derivatives: true,
+            energy: true,
+        }
+    }
+}
+
+/// The steps of a solution as a table, with the solver that produced it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DataTable {
+    pub solver: String,
+    pub termination: String,
+    pub stats: SolverStats,
+    /// The name of each column, starting with the independent variable
+    pub columns: Vec<String>,
+    /// One row per step of the solution, or per sample when resampled
+    pub rows: Vec<Vec<f64>>,
+}
+
+impl DataTable {
+    pub fn new(
+        settings: &OdeSettings,
+        solver: OdeSolver,
+        solution: &Solution,
+        export: &ExportSettings,
+    ) -> Result<Self> {
+        let names = settings.state_names();
+
+        let mut columns = vec![settings.independent_name().to_string()];
+        columns.extend(names.iter().cloned());
+
+        let mut rows = solution
+            .points()
+            .map(|(t, y)| std::iter::once(t).chain(y.iter().copied()).collect())
+            .collect::<Vec<Vec<f64>>>();
+
+        if export.derivatives {
+            columns.extend(names.iter().map(|name| format!("d{}", name)));
+            for (row, dy) in rows.iter_mut().zip(solution.dy()) {
+                row.extend(dy);
+            }
+        }
+
+        if export.energy && settings.inputs.mode == InputMode::Hamiltonian {
+            columns.push("H".to_string());
+            for (row, h) in rows.iter_mut().zip(energy(settings, solution)?) {
+                row.push(h);
+            }
+        }
+
+        Ok(Self {
+            solver: solver.name().to_string(),
+            termination: solution.termination.to_string(),
+            stats: solution.stats,
+            columns,
+            rows,
+        })
+    }
+
+    /// The solution at `n` evenly spaced points of its domain, with the state only.
+    pub fn resampled(
+        settings: &OdeSettings,
+        solver: OdeSolver,
+        solution: &Solution,
+        n: usize,
+    ) -> Self {
+        let mut columns = vec![settings.independent_name().to_string()];
+        columns.extend(settings.state_names());
+
+        let (domain, image) = solution.resample(n);
+        let rows = domain
+            .into_iter()
+            .zip(image)
+            .map(|(t, y)| std::iter::once(t).chain(y).collect())
+            .collect();
+
+        Self {
+            solver: solver.name().to_string(),
+            termination: solution.termination.to_string(),
+            stats: solution.stats,
+            columns,
+            rows,
+        }
+    }
+
+    /// The table as CSV, with a header of the column names and no metadata.
+    pub fn to_csv(&self) -> String {
+        let mut csv = self.columns.join(",") + "\n";
+
+        for row in &self.rows {
+            let row = row.iter().map(f64::to_string).collect::<Vec<_>>();
+            let _ = writeln!(csv, "{}", row.join(","));
+        }
+
+        csv
+    }
+
+    /// The table as a `.npy` file: a C-ordered float64 matrix of `rows × columns`.
+    pub fn to_npy(&self) -> Vec<u8> {
+        let mut header = format!(
+            "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, {}), }}",
+            self.rows.len(),
+            self.columns.len()
+        );
+
+        // The magic string, version, header length and header are padded to a
+        // multiple of 64 bytes, with the header ending in a newline.
+        let unpadded = 10 + header.len() + 1;
+        header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+        header.push('\n');
+
+        let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+        npy.extend((header.len() as u16).to_le_bytes());
+        npy.extend(header.as_bytes());
+        for value in self.rows.iter().flatten() {
+            npy.extend(value.to_le_bytes());
+        }
+
+        npy
+    }
+}
+
+/// The tables as CSV, with a leading solver column and the metadata of each
+/// solver in `#` comments.
+pub fn to_csv(tables: &[DataTable]) -> String {
+    let mut csv = String::new();
+
+    for table in tables {
+        let stats = &table.stats;
+        let _ = writeln!(
+            csv,
+            "# {}: {}; {} accepted steps, {} rejected, {} evaluations",
+            table.solver,
+            table.termination,
+            stats.accepted_steps,
+            stats.rejected_steps,
+            stats.function_evaluations
+        );
+    }
+
+    let columns = tables.first().map_or(&[][..], |table| &table.columns[..]);
+    let _ = writeln!(csv, "solver,{}", columns.join(","));
+
+    // The rows of each table, after its header, prefixed by its solver.
+    for table in tables {
+        for row in table.to_csv().lines().skip(1) {
+            let _ = writeln!(csv, "{},{}", table.solver, row);
+        }
+    }
+
+    csv
+}
+
+/// Writes the tables to `path` in `format`, returning the files written.
+///
+/// A `.npy` file holds a single matrix, so every table after the first is
+/// written next to `path`, suffixed with the name of its solver.
+pub fn write_data(path: &Path, format: DataFormat, tables: &[DataTable]) -> Result<Vec<PathBuf>> {
+    let write = |path: &Path, contents: &[u8]| {
+        std::fs::write(path, contents)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))
+    };
+
+    match format {
+        DataFormat::Csv => {
+            write(path, to_csv(tables).as_bytes())?;
+            Ok(vec![path.to_path_buf()])
+        }
+        DataFormat::Json => {
+            let json = serde_json::to_string_pretty(tables)
+                .map_err(|e| anyhow::anyhow!("Failed to serialize solutions: {}", e))?;
+            write(path, json.as_bytes())?;
+            Ok(vec![path.to_path_buf()])
+        }
+        DataFormat::Npy => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+            tables
+                .iter()
+                .enumerate()
+                .map(|(i, table)| {
+                    let path = if i == 0 {
+                        path.to_path_buf()
+                    } else {
+                        path.with_file_name(format!("{}_{}.npy", stem, slug(&table.solver)))
+                    };
+
+                    write(&path, &table.to_npy())?;
+                    Ok(path)
+                })
+                .collect()
+        }
+    }
+}
+
+/// The name in lowercase, with runs of other characters replaced by a dash.
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::solve_ode;
+
+    fn solve(settings: &OdeSettings) -> Solution {
+        let (t_span, ics) = settings.initial_value_problem();
+        solve_ode(settings, t_span, &ics, None).unwrap()
+    }
+
+    #[test]
+    fn table_has_a_row_per_step() {
+        let settings = OdeSettings::default();
+        let solution = solve(&settings);
+        let table = DataTable::new(
+            &settings,
+            settings.ode_solver,
+            &solution,
+            &ExportSettings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(table.columns, ["x", "y", "dy"]);
+        assert_eq!(table.rows.len(), solution.len());
+        assert!(table.rows.iter().all(|row| row.len() == 3));
+
+        let csv = table.to_csv();
+        assert_eq!(csv.lines().next(), Some("x,y,dy"));
+        assert_eq!(csv.lines().count(), solution.len() + 1);
+    }
+
+    #[test]
+    fn combined_csv_prefixes_rows_with_the_solver() {
+        let settings = OdeSettings::default();
+        let solution = solve(&settings);
+        let table = DataTable::resampled(&settings, settings.ode_solver, &solution, 5);
+
+        let csv = to_csv(&[table.clone(), table.clone()]);
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert!(lines[0].starts_with("# "));
+        assert_eq!(lines[2], "solver,x,y");
+        assert_eq!(lines.len(), 3 + 2 * 5);
+
+        let first_row = table.to_csv().lines().nth(1).unwrap().to_string();
+        assert_eq!(lines[3], format!("{},{}", table.solver, first_row));
+    }
+}
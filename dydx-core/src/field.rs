@@ -0,0 +1,98 @@
+use anyhow::Result;
+use symbolica::{
+    atom::Atom,
+    evaluate::{ExpressionEvaluator, FunctionMap, OptimizationSettings},
+};
+
+use super::settings::{OdeSettings, Projection};
+
+/// The vector field of the ODE in the plane of the plot.
+///
+/// Scalar ODEs are drawn as the direction field (1, f) of their graphs. Systems
+/// are drawn in their phase plane, with the components that are not plotted
+/// held at their initial values.
+#[derive(Debug, Clone)]
+pub struct VectorField {
+    projection: Projection,
+    evaluator: ExpressionEvaluator<f64>,
+    /// The state the plotted components are substituted into
+    base_state: Vec<f64>,
+}
+
+impl VectorField {
+    pub fn new(settings: &OdeSettings) -> Result<Self> {
+        let expressions = settings
+            .inputs
+            .parsed_expressions
+            .as_ref()
+            .map_err(|e| anyhow::anyhow!("Failed to parse expressions: {}", e))?;
+
+        let expressions = expressions
+            .iter()
+            .map(|expr| expr.as_view())
+            .collect::<Vec<_>>();
+
+        let evaluator = Atom::evaluator_multiple(
+            expressions.as_slice(),
+            &FunctionMap::new(),
+            settings.variables().as_slice(),
+            OptimizationSettings::default(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to create evaluator: {:?}", e))?
+        .map_coeff(&|x| x.into());
+
+        let mut field = Self {
+            projection: settings.projection(),
+            evaluator,
+            base_state: vec![],
+        };
+        field.update(settings);
+
+        Ok(field)
+    }
+
+    /// Updates the projection and the state held fixed from `settings`, which
+    /// unlike the expressions can change without compiling a new evaluator.
+    pub fn update(&mut self, settings: &OdeSettings) {
+        self.projection = settings.projection();
+        self.base_state = if settings.is_system() {
+            settings.ics.clone()
+        } else {
+            vec![0.0]
+        };
+    }
+
+    /// The velocity of the flow at the point `(x, y)` of the plot, or `None` where it is undefined.
+    pub fn velocity(&mut self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let mut out = vec![0.0; self.base_state.len()];
+
+        let velocity = match self.projection {
+            Projection::Graph => {
+                self.evaluator.evaluate(&[x, y], &mut out);
+                (1.0, out[0])
+            }
+            Projection::Polar => {
+                let (r, theta) = (x.hypot(y), y.atan2(x));
+                self.evaluator.evaluate(&[r, theta], &mut out);
+
+                // d/dr (r cos θ, r sin θ) along θ' = f
+                let f = out[0];
+                (
+                    theta.cos() - r * theta.sin() * f,
+                    theta.sin() + r * theta.cos() * f,
+                )
+            }
+            Projection::Phase(i, j) => {
+                let mut state = self.base_state.clone();
+                state[i] = x;
+                state[j] = y;
+
+                let in_ = std::iter::once(0.0).chain(state).collect::<Vec<_>>();
+                self.evaluator.evaluate(&in_, &mut out);
+                (out[i], out[j])
+            }
+        };
+
+        (velocity.0.is_finite() && velocity.1.is_finite()).then_some(velocity)
+    }
+}
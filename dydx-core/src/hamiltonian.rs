@@ -0,0 +1,68 @@
+use anyhow::Result;
+use symbolica::{
+    atom::{Atom, Symbol},
+    evaluate::{FunctionMap, OptimizationSettings},
+};
+
+use super::{
+    settings::{InputMode, OdeSettings},
+    solution::Solution,
+};
+
+/// Derives Hamilton's equations from the Hamiltonian `h`.
+///
+/// The state is ordered as the coordinates followed by their momenta, so the
+/// returned right-hand sides are ∂H/∂p for each coordinate followed by -∂H/∂q
+/// for each momentum.
+pub(crate) fn hamilton_equations(
+    h: &Atom,
+    coordinates: &[Symbol],
+    momenta: &[Symbol],
+) -> Vec<Atom> {
+    let dq = momenta.iter().map(|&p| h.derivative(p));
+    let dp = coordinates.iter().map(|&q| -h.derivative(q));
+
+    dq.chain(dp).collect()
+}
+
+/// Whether the Hamiltonian splits as H(q, p) = T(p) + V(q).
+///
+/// The symplectic methods in this crate are explicit only for separable Hamiltonians.
+pub(crate) fn is_separable(h: &Atom, coordinates: &[Symbol], momenta: &[Symbol]) -> bool {
+    coordinates.iter().all(|&q| {
+        let dh_dq = h.derivative(q);
+        momenta
+            .iter()
+            .all(|&p| dh_dq.derivative(p).expand() == Atom::new_num(0))
+    })
+}
+
+/// Evaluates the Hamiltonian of `settings` along every step of `solution`.
+pub fn energy(settings: &OdeSettings, solution: &Solution) -> Result<Vec<f64>> {
+    if settings.inputs.mode != InputMode::Hamiltonian {
+        anyhow::bail!("The ODE is not given by a Hamiltonian");
+    }
+
+    let hamiltonian = Atom::parse(&settings.inputs.inputs[0])
+        .map_err(|e| anyhow::anyhow!("Failed to parse Hamiltonian: {}", e))?;
+
+    let mut evaluator = hamiltonian
+        .as_view()
+        .evaluator(
+            &FunctionMap::new(),
+            settings.variables().as_slice(),
+            OptimizationSettings::default(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to create Hamiltonian evaluator: {:?}", e))?
+        .map_coeff(&|x| x.into());
+
+    let energy = solution
+        .points()
+        .map(|(t, y)| {
+            let in_ = std::iter::once(&t).chain(y).copied().collect::<Vec<_>>();
+            evaluator.evaluate_single(in_.as_slice())
+        })
+        .collect();
+
+    Ok(energy)
+}
@@ -0,0 +1,30 @@
+/// Evaluates the cubic Hermite interpolant between two solution points at `t`.
+///
+/// `f0` and `f1` are the derivatives of the solution at `t0` and `t1`, so the
+/// interpolant matches both the state and the slope at each end of the step.
+pub(crate) fn hermite(
+    (t0, y0, f0): (f64, &[f64], &[f64]),
+    (t1, y1, f1): (f64, &[f64], &[f64]),
+    t: f64,
+) -> Vec<f64> {
+    let h = t1 - t0;
+
+    if h == 0.0 {
+        return y0.to_vec();
+    }
+
+    let s = (t - t0) / h;
+    let s2 = s * s;
+    let s3 = s2 * s;
+
+    let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+    let h10 = s3 - 2.0 * s2 + s;
+    let h01 = -2.0 * s3 + 3.0 * s2;
+    let h11 = s3 - s2;
+
+    y0.iter()
+        .zip(f0)
+        .zip(y1.iter().zip(f1))
+        .map(|((&y0, &f0), (&y1, &f1))| h00 * y0 + h10 * h * f0 + h01 * y1 + h11 * h * f1)
+        .collect()
+}
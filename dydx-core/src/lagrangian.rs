@@ -0,0 +1,100 @@
+use symbolica::atom::{Atom, Symbol};
+
+/// Derives the first order system of the Euler-Lagrange equations of `l`.
+///
+/// The Euler-Lagrange equations d/dt(∂L/∂q̇) = ∂L/∂q are linear in the
+/// accelerations, M q̈ = b with M = ∂²L/∂q̇∂q̇ and
+/// b = ∂L/∂q - ∂²L/∂q̇∂q q̇ - ∂²L/∂q̇∂t, and are solved for them by Cramer's rule.
+/// The state is ordered as the coordinates followed by their velocities.
+pub(crate) fn euler_lagrange_equations(
+    l: &Atom,
+    coordinates: &[Symbol],
+    velocities: &[Symbol],
+    t: Symbol,
+) -> Result<Vec<Atom>, String> {
+    if coordinates.len() != velocities.len() {
+        return Err("Every coordinate needs a velocity".to_string());
+    }
+
+    let dl_dv = velocities
+        .iter()
+        .map(|&v| l.derivative(v))
+        .collect::<Vec<_>>();
+
+    let mass = dl_dv
+        .iter()
+        .map(|dl_dvi| {
+            velocities
+                .iter()
+                .map(|&vj| dl_dvi.derivative(vj))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let forces = coordinates
+        .iter()
+        .zip(&dl_dv)
+        .map(|(&qi, dl_dvi)| {
+            let coupling = coordinates
+                .iter()
+                .zip(velocities)
+                .fold(dl_dvi.derivative(t), |acc, (&qj, &vj)| {
+                    &acc + &(&dl_dvi.derivative(qj) * &Atom::new_var(vj))
+                });
+
+            &l.derivative(qi) - &coupling
+        })
+        .collect::<Vec<_>>();
+
+    let det = determinant(&mass);
+    if det.expand() == Atom::new_num(0) {
+        return Err("The Lagrangian is degenerate in the velocities".to_string());
+    }
+
+    let accelerations = (0..coordinates.len()).map(|i| {
+        let replaced = mass
+            .iter()
+            .zip(&forces)
+            .map(|(row, b)| {
+                let mut row = row.clone();
+                row[i] = b.clone();
+                row
+            })
+            .collect::<Vec<_>>();
+
+        &determinant(&replaced) / &det
+    });
+
+    Ok(velocities
+        .iter()
+        .map(|&v| Atom::new_var(v))
+        .chain(accelerations)
+        .collect())
+}
+
+/// The determinant of a square matrix of expressions, by Laplace expansion.
+fn determinant(m: &[Vec<Atom>]) -> Atom {
+    match m.len() {
+        0 => Atom::new_num(1),
+        1 => m[0][0].clone(),
+        n => (0..n).fold(Atom::new_num(0), |acc, j| {
+            let minor = m[1..]
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|&(k, _)| k != j)
+                        .map(|(_, a)| a.clone())
+                        .collect()
+                })
+                .collect::<Vec<Vec<_>>>();
+
+            let term = &m[0][j] * &determinant(&minor);
+            if j % 2 == 0 {
+                &acc + &term
+            } else {
+                &acc - &term
+            }
+        }),
+    }
+}
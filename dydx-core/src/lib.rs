@@ -0,0 +1,62 @@
+//! The expression-driven ODE engine behind dydx.
+//!
+//! ODEs are entered as strings, parsed with Symbolica and compiled to
+//! evaluators, so that scalar equations, first order systems, Hamiltonians and
+//! Lagrangians can all be solved from their symbolic form:
+//!
+//! ```
+//! use dydx_core::{solve_ode, OdeInputs, OdeSettings};
+//!
+//! let mut settings = OdeSettings::default();
+//! settings.inputs = OdeInputs::system(&["x", "v"], &["v", "-x"]);
+//! settings.update_inputs();
+//! settings.ics = vec![1.0, 0.0];
+//! settings.integration_length = std::f64::consts::TAU;
+//!
+//! // After one period the oscillator is back where it started.
+//! let (t_span, ics) = settings.initial_value_problem();
+//! let solution = solve_ode(&settings, t_span, &ics, None)?;
+//! assert!((solution.y().last().unwrap()[0] - 1.0).abs() < 1e-3);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! - [`OdeSettings`] holds the inputs, initial conditions, solver and its parameters.
+//! - [`solve_ode`] integrates them into a [`Solution`], a dense output with the
+//!   located events, why the integration stopped and solver statistics.
+//! - [`ExpressionODEProblem`] exposes the parsed ODE to peroxide's integrators.
+//! - [`GeneralSolution`], [`ConvergenceStudy`] and [`energy`] analyse solutions,
+//!   and [`DataTable`] exports them.
+
+mod closed_form;
+mod convergence;
+mod dense;
+mod events;
+mod export;
+mod field;
+mod hamiltonian;
+mod interpolation;
+mod lagrangian;
+mod parameters;
+mod schemes;
+mod settings;
+mod solution;
+mod solver;
+mod stepper;
+mod stiff;
+mod symplectic;
+mod termination;
+
+pub use closed_form::{ClosedForm, GeneralSolution, OdeClass, SolutionForm};
+pub use convergence::{ConvergenceSeries, ConvergenceSettings, ConvergenceStudy, Reference};
+pub use dense::DenseSolution;
+pub use events::{EventDirection, EventHit, EventSpec};
+pub use export::{to_csv, write_data, DataFormat, DataTable, ExportSettings};
+pub use field::VectorField;
+pub use hamiltonian::energy;
+pub use parameters::*;
+pub use schemes::*;
+pub use settings::*;
+pub use solution::{RejectedAttempt, Solution, SolverStats, StepRecord};
+pub use solver::{solve_ode, ExpressionODEProblem};
+pub use stepper::MethodSwitch;
+pub use termination::{Termination, TerminationSettings, Viewport};
@@ -0,0 +1,132 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Tolerance(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SafetyFactor(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MaxStepSize(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MinStepSize(pub f64);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MaxSteps(pub usize);
+
+/// The step size control parameters shared by the adaptive solvers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SolverParameters {
+    pub tolerance: Tolerance,
+    pub safety_factor: SafetyFactor,
+    pub min_step_size: MinStepSize,
+    pub max_step_size: MaxStepSize,
+    /// The maximum number of attempts at a single step
+    pub max_steps: MaxSteps,
+    /// The initial step size, and the step size of the fixed-step methods
+    pub initial_step_size: f64,
+}
+
+impl Default for SolverParameters {
+    fn default() -> Self {
+        Self {
+            tolerance: Tolerance(1e-4),
+            safety_factor: SafetyFactor(0.9),
+            min_step_size: MinStepSize(1e-6),
+            max_step_size: MaxStepSize(1e-2),
+            max_steps: MaxSteps(1000),
+            initial_step_size: 1e-3,
+        }
+    }
+}
+
+impl SolverParameters {
+    /// Checks that the parameters describe a usable step size control, as the
+    /// solvers clamp step sizes between the bounds and would panic otherwise.
+    /// An infinite tolerance is allowed, to pin the fixed step size.
+    pub fn validate(&self) -> Result<()> {
+        let Self {
+            tolerance: Tolerance(tolerance),
+            safety_factor: SafetyFactor(safety_factor),
+            min_step_size: MinStepSize(min_step_size),
+            max_step_size: MaxStepSize(max_step_size),
+            max_steps: MaxSteps(max_steps),
+            initial_step_size,
+        } = *self;
+
+        if tolerance.is_nan() || tolerance <= 0.0 {
+            bail!("The tolerance must be positive, got {}", tolerance);
+        }
+        if !(safety_factor > 0.0 && safety_factor <= 1.0) {
+            bail!("The safety factor must be in (0, 1], got {}", safety_factor);
+        }
+        if !(min_step_size.is_finite() && min_step_size >= 0.0) {
+            bail!(
+                "The minimum step size must be non-negative, got {}",
+                min_step_size
+            );
+        }
+        if !(max_step_size.is_finite() && max_step_size > 0.0) {
+            bail!(
+                "The maximum step size must be positive, got {}",
+                max_step_size
+            );
+        }
+        if min_step_size > max_step_size {
+            bail!(
+                "The minimum step size {} exceeds the maximum step size {}",
+                min_step_size,
+                max_step_size
+            );
+        }
+        if !(initial_step_size.is_finite() && initial_step_size > 0.0) {
+            bail!(
+                "The initial step size must be positive, got {}",
+                initial_step_size
+            );
+        }
+        if max_steps == 0 {
+            bail!("At least one attempt per step is needed");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_parameters_are_valid() {
+        assert!(SolverParameters::default().validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_reversed_step_bounds() {
+        let parameters = SolverParameters {
+            min_step_size: MinStepSize(0.1),
+            max_step_size: MaxStepSize(0.01),
+            ..Default::default()
+        };
+        assert!(parameters.validate().is_err());
+    }
+
+    #[test]
+    fn rejects_nan_tolerance() {
+        let parameters = SolverParameters {
+            tolerance: Tolerance(f64::NAN),
+            ..Default::default()
+        };
+        assert!(parameters.validate().is_err());
+
+        let pinned = SolverParameters {
+            tolerance: Tolerance(f64::INFINITY),
+            ..Default::default()
+        };
+        assert!(pinned.validate().is_ok());
+    }
+}
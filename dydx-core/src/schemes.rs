@@ -0,0 +1,125 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ExplicitMethod {
+    /// Ralston's 3rd order method
+    RALS3,
+    /// Runge-Kutta 4th order method
+    RK4,
+    /// Ralston's 4th order method
+    RALS4,
+    /// Runge-Kutta 5th order method
+    RK5,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ImplicitMethod {
+    /// Gauss-Legendre 4th order method
+    GL4,
+    /// Rosenbrock 2/3rd order method (ode23s)
+    ROS23,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum EmbeddedMethod {
+    /// Bogacki-Shampine 2/3rd order method
+    BS23,
+    /// Runge-Kutta-Fehlberg 4/5th method
+    RKF45,
+    /// Dormand-Prince 4/5th order method
+    DP45,
+    /// Tsitouras 4/5th order method
+    TSIT45,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SymplecticMethod {
+    /// Störmer-Verlet (leapfrog) 2nd order method
+    Verlet,
+    /// Yoshida 4th order method
+    Yoshida4,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum OdeSolver {
+    Explicit(ExplicitMethod),
+    Implicit(ImplicitMethod),
+    Embedded(EmbeddedMethod),
+    /// Symplectic methods for separable Hamiltonian systems
+    Symplectic(SymplecticMethod),
+    /// Dormand-Prince, switching to Rosenbrock while the problem is stiff
+    Auto,
+}
+
+impl OdeSolver {
+    pub const ALL: [OdeSolver; 13] = [
+        OdeSolver::Auto,
+        OdeSolver::Explicit(ExplicitMethod::RALS3),
+        OdeSolver::Explicit(ExplicitMethod::RK4),
+        OdeSolver::Explicit(ExplicitMethod::RALS4),
+        OdeSolver::Explicit(ExplicitMethod::RK5),
+        OdeSolver::Embedded(EmbeddedMethod::BS23),
+        OdeSolver::Embedded(EmbeddedMethod::RKF45),
+        OdeSolver::Embedded(EmbeddedMethod::DP45),
+        OdeSolver::Embedded(EmbeddedMethod::TSIT45),
+        OdeSolver::Implicit(ImplicitMethod::GL4),
+        OdeSolver::Implicit(ImplicitMethod::ROS23),
+        OdeSolver::Symplectic(SymplecticMethod::Verlet),
+        OdeSolver::Symplectic(SymplecticMethod::Yoshida4),
+    ];
+
+    /// Whether the solver needs the Jacobian of the ODE.
+    pub fn needs_jacobian(&self) -> bool {
+        matches!(
+            self,
+            OdeSolver::Implicit(ImplicitMethod::GL4 | ImplicitMethod::ROS23) | OdeSolver::Auto
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OdeSolver::Explicit(ExplicitMethod::RALS3) => "Ralston 3",
+            OdeSolver::Explicit(ExplicitMethod::RK4) => "RK4",
+            OdeSolver::Explicit(ExplicitMethod::RALS4) => "Ralston 4",
+            OdeSolver::Explicit(ExplicitMethod::RK5) => "RK5",
+            OdeSolver::Implicit(ImplicitMethod::GL4) => "Gauss-Legendre 4",
+            OdeSolver::Implicit(ImplicitMethod::ROS23) => "Rosenbrock 23",
+            OdeSolver::Embedded(EmbeddedMethod::BS23) => "Bogacki-Shampine 23",
+            OdeSolver::Embedded(EmbeddedMethod::RKF45) => "RKF45",
+            OdeSolver::Embedded(EmbeddedMethod::DP45) => "Dormand-Prince 45",
+            OdeSolver::Embedded(EmbeddedMethod::TSIT45) => "Tsitouras 45",
+            OdeSolver::Symplectic(SymplecticMethod::Verlet) => "Störmer-Verlet",
+            OdeSolver::Symplectic(SymplecticMethod::Yoshida4) => "Yoshida 4",
+            OdeSolver::Auto => "Auto",
+        }
+    }
+}
+
+/// Parses a solver by its name, ignoring case, spaces and punctuation, so that
+/// "Dormand-Prince 45" can be given as `dormand-prince-45`.
+impl FromStr for OdeSolver {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalize = |name: &str| {
+            name.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        };
+
+        OdeSolver::ALL
+            .into_iter()
+            .find(|solver| normalize(solver.name()) == normalize(s))
+            .ok_or_else(|| {
+                let names = OdeSolver::ALL.map(|solver| solver.name());
+                format!(
+                    "Unknown solver {:?}, expected one of: {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
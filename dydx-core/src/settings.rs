@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use symbolica::{
+    atom::{Atom, Symbol},
+    symb,
+};
+
+use super::{
+    events::EventSpec,
+    hamiltonian::hamilton_equations,
+    lagrangian::euler_lagrange_equations,
+    parameters::SolverParameters,
+    schemes::{EmbeddedMethod, OdeSolver},
+    termination::TerminationSettings,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OdeSettings {
+    pub integration_length: f64,
+    pub ode_solver: OdeSolver,
+    pub parameters: SolverParameters,
+    pub ics: Vec<f64>,
+    pub coordinate: OdeCoordinate,
+    pub dimensions: u8,
+    /// The state components plotted against each other for systems
+    pub phase_axes: (usize, usize),
+    pub inputs: OdeInputs,
+    pub events: Vec<EventSpec>,
+    pub termination: TerminationSettings,
+    #[serde(skip, default = "default_symbols")]
+    pub(crate) symbols: HashMap<String, Symbol>,
+}
+
+fn default_symbols() -> HashMap<String, Symbol> {
+    ["x", "y", "r", "theta"]
+        .iter()
+        .map(|s| (s.to_string(), symb!(s)))
+        .collect()
+}
+
+/// The placeholder for parsed expressions, which are not serialized but
+/// rebuilt from their inputs once loaded.
+pub(super) fn unparsed<T>() -> Result<T, String> {
+    Err("Not parsed yet".to_string())
+}
+
+impl Default for OdeSettings {
+    fn default() -> Self {
+        let expr = "x^2 - 7y - 10";
+
+        Self {
+            integration_length: 10.0,
+            ode_solver: OdeSolver::Embedded(EmbeddedMethod::RKF45),
+            parameters: SolverParameters::default(),
+            ics: vec![1.0, 1.0],
+            coordinate: OdeCoordinate::Cartesian,
+            dimensions: 1,
+            phase_axes: (0, 1),
+            inputs: OdeInputs {
+                mode: InputMode::Scalar,
+                inputs: vec![expr.to_string()],
+                variables: vec![],
+                conjugates: vec![],
+                parsed_expressions: Ok(vec![Atom::parse(expr).unwrap()]),
+            },
+            events: vec![],
+            termination: TerminationSettings::default(),
+            symbols: default_symbols(),
+        }
+    }
+}
+
+impl OdeSettings {
+    /// The name of the independent variable.
+    pub fn independent_name(&self) -> &str {
+        match (self.inputs.mode, self.coordinate) {
+            (InputMode::Scalar, OdeCoordinate::Cartesian) => "x",
+            (InputMode::Scalar, OdeCoordinate::Polar) => "r",
+            _ => "t",
+        }
+    }
+
+    /// The names of the components of the state, in order.
+    pub fn state_names(&self) -> Vec<String> {
+        match (self.inputs.mode, self.coordinate) {
+            (InputMode::Scalar, OdeCoordinate::Cartesian) => vec!["y".to_string()],
+            (InputMode::Scalar, OdeCoordinate::Polar) => vec!["theta".to_string()],
+            (InputMode::System, _) => self.inputs.variables.clone(),
+            (InputMode::Hamiltonian | InputMode::Lagrangian, _) => self
+                .inputs
+                .variables
+                .iter()
+                .chain(&self.inputs.conjugates)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Whether the ODE is a system, whose state is plotted as a trajectory in phase space.
+    pub fn is_system(&self) -> bool {
+        self.inputs.mode != InputMode::Scalar
+    }
+
+    /// How the solution is projected onto the plane of the plot.
+    pub fn projection(&self) -> Projection {
+        match (self.inputs.mode, self.coordinate) {
+            (InputMode::Scalar, OdeCoordinate::Cartesian) => Projection::Graph,
+            (InputMode::Scalar, OdeCoordinate::Polar) => Projection::Polar,
+            _ => {
+                let last = (self.dimensions as usize).saturating_sub(1);
+                let (i, j) = self.phase_axes;
+                Projection::Phase(i.min(last), j.min(last))
+            }
+        }
+    }
+
+    /// The time span and initial state of the integration.
+    ///
+    /// The initial condition of a scalar ODE is the point (x₀, y₀) of the plot,
+    /// so in polar coordinates it starts from the radius and angle of that point
+    /// and ends at the radius of (x₀ + length, y₀).
+    pub fn initial_value_problem(&self) -> ((f64, f64), Vec<f64>) {
+        if self.is_system() {
+            return ((0.0, self.integration_length), self.ics.clone());
+        }
+
+        let (x0, y0) = (self.ics[0], self.ics[1]);
+        let xn = x0 + self.integration_length;
+
+        match self.coordinate {
+            OdeCoordinate::Cartesian => ((x0, xn), vec![y0]),
+            OdeCoordinate::Polar => ((x0.hypot(y0), xn.hypot(y0)), vec![y0.atan2(x0)]),
+        }
+    }
+
+    /// Checks the settings that can't be solved with, such as a negative
+    /// integration length or reversed step size bounds, as loaded from a file.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(self.integration_length.is_finite() && self.integration_length > 0.0) {
+            anyhow::bail!(
+                "The integration length must be positive, got {}",
+                self.integration_length
+            );
+        }
+
+        if let Some(ic) = self.ics.iter().find(|ic| !ic.is_finite()) {
+            anyhow::bail!("The initial conditions must be finite, got {}", ic);
+        }
+
+        let termination = &self.termination;
+        if termination.min_step_size.is_nan() || termination.min_step_size < 0.0 {
+            anyhow::bail!(
+                "The minimum step size to stop at must be non-negative, got {}",
+                termination.min_step_size
+            );
+        }
+        if termination.viewport_margin.is_nan() || termination.viewport_margin < 0.0 {
+            anyhow::bail!(
+                "The viewport margin must be non-negative, got {}",
+                termination.viewport_margin
+            );
+        }
+        if let Some(max_norm) = termination
+            .max_norm
+            .filter(|norm| norm.is_nan() || *norm <= 0.0)
+        {
+            anyhow::bail!("The state bound must be positive, got {}", max_norm);
+        }
+
+        self.parameters.validate()
+    }
+
+    /// Re-parses the inputs after they changed, updating the dimensions and initial conditions.
+    pub fn update_inputs(&mut self) {
+        self.inputs.parse_expressions();
+        self.dimensions = self.state_names().len() as u8;
+
+        let ics = if self.is_system() {
+            self.dimensions as usize
+        } else {
+            2
+        };
+        self.ics.resize(ics, 0.0);
+    }
+
+    /// Rebuilds the parsed expressions after the settings were deserialized.
+    pub fn rebuild(&mut self) {
+        self.update_inputs();
+        for event in &mut self.events {
+            event.parse_expression();
+        }
+    }
+
+    pub(crate) fn symbol(&self, name: &str) -> Symbol {
+        self.symbols
+            .get(name)
+            .copied()
+            .unwrap_or_else(|| symb!(name))
+    }
+
+    /// The symbols of the variables the ODE expressions are evaluated over, in evaluation order.
+    pub(crate) fn variable_symbols(&self) -> Vec<Symbol> {
+        std::iter::once(self.independent_name().to_string())
+            .chain(self.state_names())
+            .map(|s| self.symbol(&s))
+            .collect()
+    }
+
+    /// The variables the ODE expressions are evaluated over, in evaluation
+    /// order: the independent variable followed by the state.
+    pub fn variables(&self) -> Vec<Atom> {
+        self.variable_symbols()
+            .into_iter()
+            .map(Atom::new_var)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum OdeCoordinate {
+    Cartesian,
+    Polar,
+}
+
+/// How a point `(t, y)` of a solution is projected onto the plane of the plot.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Projection {
+    /// The graph (x, y) of a scalar solution
+    Graph,
+    /// The graph of a scalar solution in polar coordinates (r, θ)
+    Polar,
+    /// Two components of the state of a system
+    Phase(usize, usize),
+}
+
+impl Projection {
+    pub fn project(&self, t: f64, y: &[f64]) -> (f64, f64) {
+        match *self {
+            Projection::Graph => (t, y[0]),
+            Projection::Polar => {
+                let (r, theta) = (t, y[0]);
+                (r * theta.cos(), r * theta.sin())
+            }
+            Projection::Phase(i, j) => (y[i], y[j]),
+        }
+    }
+}
+
+/// How the ODE is entered by the user.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum InputMode {
+    /// A single equation y' = f(x, y)
+    Scalar,
+    /// A first order system, one equation per variable
+    System,
+    /// A Hamiltonian H(q, p), from which Hamilton's equations are derived
+    Hamiltonian,
+    /// A Lagrangian L(q, q̇, t), from which the Euler-Lagrange equations are derived
+    Lagrangian,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OdeInputs {
+    pub mode: InputMode,
+    /// The right-hand sides of a scalar ODE or system, or the Hamiltonian
+    pub inputs: Vec<String>,
+    /// The state variables of a system, or the generalized coordinates
+    pub variables: Vec<String>,
+    /// The conjugate momenta or the velocities of the generalized coordinates
+    pub conjugates: Vec<String>,
+    /// The right-hand sides of the first order system
+    #[serde(skip, default = "unparsed")]
+    pub parsed_expressions: Result<Vec<Atom>, String>,
+}
+
+impl OdeInputs {
+    /// Inputs for a system with the given variables and right-hand sides.
+    pub fn system(variables: &[&str], inputs: &[&str]) -> Self {
+        let mut system = Self {
+            mode: InputMode::System,
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            variables: variables.iter().map(|s| s.to_string()).collect(),
+            conjugates: vec![],
+            parsed_expressions: Ok(vec![]),
+        };
+
+        system.parse_expressions();
+        system
+    }
+
+    /// Inputs for a Hamiltonian in the given coordinates and momenta.
+    pub fn hamiltonian(hamiltonian: &str, coordinates: &[&str], momenta: &[&str]) -> Self {
+        let mut system = Self {
+            mode: InputMode::Hamiltonian,
+            inputs: vec![hamiltonian.to_string()],
+            variables: coordinates.iter().map(|s| s.to_string()).collect(),
+            conjugates: momenta.iter().map(|s| s.to_string()).collect(),
+            parsed_expressions: Ok(vec![]),
+        };
+
+        system.parse_expressions();
+        system
+    }
+
+    /// Inputs for a Lagrangian in the given coordinates and velocities.
+    pub fn lagrangian(lagrangian: &str, coordinates: &[&str], velocities: &[&str]) -> Self {
+        let mut system = Self {
+            mode: InputMode::Lagrangian,
+            inputs: vec![lagrangian.to_string()],
+            variables: coordinates.iter().map(|s| s.to_string()).collect(),
+            conjugates: velocities.iter().map(|s| s.to_string()).collect(),
+            parsed_expressions: Ok(vec![]),
+        };
+
+        system.parse_expressions();
+        system
+    }
+
+    pub fn parse_expressions(&mut self) {
+        let parsed = self
+            .inputs
+            .iter()
+            .map(|input| Atom::parse(input).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<Atom>, String>>();
+
+        self.parsed_expressions = match self.mode {
+            InputMode::Scalar | InputMode::System => parsed,
+            InputMode::Hamiltonian => parsed.and_then(|parsed| {
+                let hamiltonian = parsed.first().ok_or("No Hamiltonian given")?;
+
+                if self.variables.len() != self.conjugates.len() {
+                    return Err("Every coordinate needs a conjugate momentum".to_string());
+                }
+
+                let coordinates = self.variables.iter().map(|q| symb!(q.as_str()));
+                let momenta = self.conjugates.iter().map(|p| symb!(p.as_str()));
+
+                Ok(hamilton_equations(
+                    hamiltonian,
+                    &coordinates.collect::<Vec<_>>(),
+                    &momenta.collect::<Vec<_>>(),
+                ))
+            }),
+            InputMode::Lagrangian => parsed.and_then(|parsed| {
+                let lagrangian = parsed.first().ok_or("No Lagrangian given")?;
+
+                let coordinates = self.variables.iter().map(|q| symb!(q.as_str()));
+                let velocities = self.conjugates.iter().map(|v| symb!(v.as_str()));
+
+                euler_lagrange_equations(
+                    lagrangian,
+                    &coordinates.collect::<Vec<_>>(),
+                    &velocities.collect::<Vec<_>>(),
+                    symb!("t"),
+                )
+            }),
+        };
+    }
+}
@@ -0,0 +1,133 @@
+use serde::Serialize;
+
+use super::{
+    dense::DenseSolution, events::EventHit, stepper::MethodSwitch, termination::Termination,
+};
+
+/// Counters describing the work done by the solver.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SolverStats {
+    pub accepted_steps: usize,
+    pub rejected_steps: usize,
+    /// Evaluations of the right-hand side made by the integrator
+    pub function_evaluations: usize,
+    pub jacobian_evaluations: usize,
+}
+
+/// An attempted step that was rejected before the step was accepted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RejectedAttempt {
+    /// The step size attempted
+    pub dt: f64,
+    /// The local error estimate that rejected it, for methods that provide one
+    pub error: Option<f64>,
+}
+
+/// Diagnostics of a single accepted step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepRecord {
+    /// The value of the independent variable at the start of the step
+    pub t: f64,
+    /// The step size taken, cut short when a terminal event ends the step
+    pub dt: f64,
+    /// The state at the end of the step
+    pub y: Vec<f64>,
+    /// The local error estimate, for methods that provide one
+    pub error: Option<f64>,
+    /// The attempts rejected before the step was accepted, in order
+    pub rejected: Vec<RejectedAttempt>,
+}
+
+/// The numerical solution of an ODE over its integration span.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    dense: DenseSolution,
+    pub events: Vec<EventHit>,
+    pub termination: Termination,
+    pub stats: SolverStats,
+    /// Method switches made by the `Auto` solver
+    pub switches: Vec<MethodSwitch>,
+    /// The diagnostics of each accepted step, in order
+    pub steps: Vec<StepRecord>,
+}
+
+impl Solution {
+    pub fn new(
+        dense: DenseSolution,
+        events: Vec<EventHit>,
+        termination: Termination,
+        stats: SolverStats,
+    ) -> Self {
+        Self {
+            dense,
+            events,
+            termination,
+            stats,
+            switches: vec![],
+            steps: vec![],
+        }
+    }
+
+    /// The values of the independent variable at each step.
+    pub fn t(&self) -> &[f64] {
+        &self.dense.t
+    }
+
+    /// The state at each step, one row per value of `t`.
+    pub fn y(&self) -> &[Vec<f64>] {
+        &self.dense.y
+    }
+
+    /// The derivative of the state at each step.
+    pub fn dy(&self) -> &[Vec<f64>] {
+        &self.dense.dy
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// The number of components of the state.
+    pub fn dimensions(&self) -> usize {
+        self.dense.y.first().map_or(0, |y| y.len())
+    }
+
+    /// The values of the `i`th state component at each step.
+    pub fn component(&self, i: usize) -> Vec<f64> {
+        self.dense.y.iter().map(|y| y[i]).collect()
+    }
+
+    /// Iterates over `(t, y)` pairs of the steps.
+    pub fn points(&self) -> impl Iterator<Item = (f64, &[f64])> {
+        self.dense
+            .t
+            .iter()
+            .zip(&self.dense.y)
+            .map(|(&t, y)| (t, y.as_slice()))
+    }
+
+    pub fn domain(&self) -> Option<(f64, f64)> {
+        self.dense.domain()
+    }
+
+    pub fn dense(&self) -> &DenseSolution {
+        &self.dense
+    }
+
+    /// Evaluates the solution at `t`, or `None` if `t` lies outside of the domain.
+    pub fn eval(&self, t: f64) -> Option<Vec<f64>> {
+        self.dense.eval(t)
+    }
+
+    pub fn sample(&self, t_span: (f64, f64), n: usize) -> (Vec<f64>, Vec<Vec<f64>>) {
+        self.dense.sample(t_span, n)
+    }
+
+    pub fn resample(&self, n: usize) -> (Vec<f64>, Vec<Vec<f64>>) {
+        self.dense.resample(n)
+    }
+}
@@ -0,0 +1,433 @@
+use std::cell::Cell;
+
+use anyhow::Result;
+use peroxide::fuga::*;
+use symbolica::{
+    atom::Atom,
+    evaluate::{ExpressionEvaluator, FunctionMap, OptimizationSettings},
+};
+use tracing::{debug, debug_span};
+
+use super::{
+    dense::DenseSolution,
+    events::EventFunctions,
+    hamiltonian::is_separable,
+    parameters::SolverParameters,
+    schemes::{EmbeddedMethod, ExplicitMethod, ImplicitMethod, OdeSolver},
+    settings::{InputMode, OdeSettings},
+    solution::{Solution, SolverStats, StepRecord},
+    stepper::{JacobianProblem, Stepper, Tableau},
+    stiff::{AutoStepper, GaussLegendre4, Rosenbrock23},
+    symplectic::Symplectic,
+    termination::{Termination, TerminationCheck, Viewport},
+};
+
+struct MaxStepODESolver<S: Stepper> {
+    stepper: S,
+}
+
+/// Wraps an ODE problem to count the evaluations of its right-hand side and Jacobian.
+struct CountingProblem<'a, P: JacobianProblem> {
+    problem: &'a P,
+    evaluations: Cell<usize>,
+    jacobian_evaluations: Cell<usize>,
+}
+
+impl<'a, P: JacobianProblem> CountingProblem<'a, P> {
+    fn new(problem: &'a P) -> Self {
+        Self {
+            problem,
+            evaluations: Cell::new(0),
+            jacobian_evaluations: Cell::new(0),
+        }
+    }
+}
+
+impl<P: JacobianProblem> ODEProblem for CountingProblem<'_, P> {
+    fn rhs(&self, t: f64, y: &[f64], dy: &mut [f64]) -> Result<()> {
+        self.evaluations.set(self.evaluations.get() + 1);
+        self.problem.rhs(t, y, dy)
+    }
+}
+
+impl<P: JacobianProblem> JacobianProblem for CountingProblem<'_, P> {
+    fn jacobian(&self, t: f64, y: &[f64], jac: &mut [f64], dfdt: &mut [f64]) -> Result<()> {
+        self.jacobian_evaluations
+            .set(self.jacobian_evaluations.get() + 1);
+        self.problem.jacobian(t, y, jac, dfdt)
+    }
+}
+
+/// The ODE given by the parsed expressions of `OdeSettings`, as a peroxide
+/// `ODEProblem` that any of its integrators can solve.
+pub struct ExpressionODEProblem {
+    dimensions: u8,
+    evaluator: ExpressionEvaluator<f64>,
+    /// Evaluates ∂f/∂y in row-major order followed by ∂f/∂t
+    jacobian: Option<ExpressionEvaluator<f64>>,
+}
+
+impl ExpressionODEProblem {
+    pub fn create(settings: &OdeSettings) -> Result<Self> {
+        let expressions = settings
+            .inputs
+            .parsed_expressions
+            .clone()
+            .map_err(|e| anyhow::anyhow!("Failed to parse expressions: {}", e))?;
+
+        let expressions = expressions
+            .iter()
+            .map(|expr| expr.as_view())
+            .collect::<Vec<_>>();
+
+        let symbols = settings.variables();
+
+        let evaluator = Atom::evaluator_multiple(
+            expressions.as_slice(),
+            &FunctionMap::new(),
+            symbols.as_slice(),
+            OptimizationSettings::default(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to create evaluator: {:?}", e))?
+        .map_coeff(&|x| x.into());
+
+        Ok(Self {
+            dimensions: settings.dimensions,
+            evaluator,
+            jacobian: None,
+        })
+    }
+
+    /// Derives the Jacobian of the ODE symbolically, for use by the stiff solvers.
+    pub fn with_jacobian(mut self, settings: &OdeSettings) -> Result<Self> {
+        let expressions = settings
+            .inputs
+            .parsed_expressions
+            .clone()
+            .map_err(|e| anyhow::anyhow!("Failed to parse expressions: {}", e))?;
+
+        let symbols = settings.variable_symbols();
+        let (t, y) = symbols
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("ODE has no variables"))?;
+
+        let derivatives = expressions
+            .iter()
+            .flat_map(|expr| y.iter().map(|&y| expr.derivative(y)))
+            .chain(expressions.iter().map(|expr| expr.derivative(*t)))
+            .collect::<Vec<_>>();
+
+        let derivatives = derivatives
+            .iter()
+            .map(|expr| expr.as_view())
+            .collect::<Vec<_>>();
+
+        let jacobian = Atom::evaluator_multiple(
+            derivatives.as_slice(),
+            &FunctionMap::new(),
+            settings.variables().as_slice(),
+            OptimizationSettings::default(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to create Jacobian evaluator: {:?}", e))?
+        .map_coeff(&|x| x.into());
+
+        self.jacobian = Some(jacobian);
+        Ok(self)
+    }
+}
+
+impl<S: Stepper> MaxStepODESolver<S> {
+    /// Integrates `problem` over `t_span`, locating any `events` along the way.
+    ///
+    /// The integration stops early when a terminal event is hit, with the final
+    /// point of the solution placed exactly on the event, or when one of the
+    /// `termination` conditions is met.
+    fn integrate<P: JacobianProblem>(
+        &self,
+        problem: &P,
+        events: Option<&EventFunctions>,
+        termination: Option<&TerminationCheck>,
+        t_span: (f64, f64),
+        dt: f64,
+        initial_conditions: &[f64],
+    ) -> Result<Solution> {
+        let counter = CountingProblem::new(problem);
+        let mut stats = SolverStats::default();
+
+        let mut t = t_span.0;
+        let mut dt = dt;
+        let mut y = initial_conditions.to_vec();
+        let mut t_vec = vec![t];
+        let mut y_vec = vec![y.clone()];
+        let mut hits = vec![];
+        let mut steps = vec![];
+        let mut reason = Termination::Completed;
+
+        let mut g = events.map(|events| events.evaluate(t, &y));
+
+        while t < t_span.1 {
+            let (t_prev, y_prev) = (t, y.clone());
+            let step = self.stepper.step(&counter, t, &mut y, dt);
+
+            if let Err(e) = &step {
+                if let Some(ODEError::ReachedMaxStepIter) = e.downcast_ref() {
+                    reason = Termination::MaxStepIterations;
+                    break;
+                }
+            }
+
+            let step = step?;
+
+            t += step.dt;
+            stats.accepted_steps += 1;
+            stats.rejected_steps += step.rejected.len();
+
+            // Recorded once the end of the step is known to be kept in the solution.
+            let record = |dt: f64, y: &[f64]| StepRecord {
+                t: t_prev,
+                dt,
+                y: y.to_vec(),
+                error: step.error,
+                rejected: step.rejected.clone(),
+            };
+
+            let state_reason = termination.and_then(|check| check.check_state(t, &y));
+            if state_reason == Some(Termination::NonFinite) {
+                reason = Termination::NonFinite;
+                break;
+            }
+
+            if let (Some(events), Some(g_prev)) = (events, &g) {
+                let g_next = events.evaluate(t, &y);
+                let step_hits =
+                    events.locate(problem, (t_prev, &y_prev, g_prev), (t, &y, &g_next))?;
+
+                if let Some(terminal) = step_hits.iter().find(|hit| hit.terminal) {
+                    debug!(target: "metrics", t = terminal.t, event = terminal.event, "Terminal event");
+                    steps.push(record(terminal.t - t_prev, &terminal.y));
+                    t_vec.push(terminal.t);
+                    y_vec.push(terminal.y.clone());
+                    reason = Termination::Event(terminal.event);
+                    hits.extend(step_hits);
+                    break;
+                }
+
+                hits.extend(step_hits);
+                g = Some(g_next);
+            }
+
+            steps.push(record(step.dt, &y));
+            t_vec.push(t);
+            y_vec.push(y.clone());
+            dt = step.dt_next;
+
+            let step_reason = termination.and_then(|check| check.check_step(dt));
+            if let Some(stop) = state_reason.or(step_reason) {
+                reason = stop;
+                break;
+            }
+        }
+
+        debug!(target: "metrics", %reason, t, "Integration finished");
+
+        stats.function_evaluations = counter.evaluations.get();
+        stats.jacobian_evaluations = counter.jacobian_evaluations.get();
+        debug!(target: "metrics", ?stats);
+
+        let dense = DenseSolution::new(problem, t_vec, y_vec)?;
+        let mut solution = Solution::new(dense, hits, reason, stats);
+        solution.switches = self.stepper.switches();
+        solution.steps = steps;
+
+        Ok(solution)
+    }
+}
+
+impl ODEProblem for ExpressionODEProblem {
+    fn rhs(&self, t: f64, y: &[f64], dy: &mut [f64]) -> Result<()> {
+        if y.len() != self.dimensions as usize {
+            anyhow::bail!(
+                "y has the wrong length. Expected {}, got {}",
+                self.dimensions,
+                y.len()
+            );
+        }
+
+        if dy.len() != self.dimensions as usize {
+            anyhow::bail!(
+                "dy has the wrong length. Expected {}, got {}",
+                self.dimensions,
+                dy.len()
+            );
+        }
+
+        let in_ = std::iter::once(&t).chain(y).copied().collect::<Vec<_>>();
+
+        let evaluator = &mut self.evaluator.clone();
+        evaluator.evaluate(in_.as_slice(), dy);
+
+        Ok(())
+    }
+}
+
+impl JacobianProblem for ExpressionODEProblem {
+    fn jacobian(&self, t: f64, y: &[f64], jac: &mut [f64], dfdt: &mut [f64]) -> Result<()> {
+        let Some(jacobian) = &self.jacobian else {
+            anyhow::bail!("The Jacobian of the ODE was not derived");
+        };
+
+        let n = self.dimensions as usize;
+        let in_ = std::iter::once(&t).chain(y).copied().collect::<Vec<_>>();
+        let mut out = vec![0.0; n * n + n];
+
+        let evaluator = &mut jacobian.clone();
+        evaluator.evaluate(in_.as_slice(), &mut out);
+
+        jac.copy_from_slice(&out[..n * n]);
+        dfdt.copy_from_slice(&out[n * n..]);
+
+        Ok(())
+    }
+}
+
+/// Integrates `problem` with the method selected by `solver`.
+fn integrate_with<P: JacobianProblem>(
+    solver: OdeSolver,
+    parameters: &SolverParameters,
+    problem: &P,
+    events: Option<&EventFunctions>,
+    termination: Option<&TerminationCheck>,
+    t_span: (f64, f64),
+    ics: &[f64],
+) -> Result<Solution> {
+    let SolverParameters {
+        tolerance,
+        safety_factor,
+        min_step_size,
+        max_step_size,
+        max_steps,
+        initial_step_size: dt,
+    } = *parameters;
+
+    let (tol, sf, min, max, iter) = (
+        tolerance.0,
+        safety_factor.0,
+        min_step_size.0,
+        max_step_size.0,
+        max_steps.0,
+    );
+
+    macro_rules! integrate {
+        ($stepper:expr) => {
+            MaxStepODESolver { stepper: $stepper }.integrate(
+                problem,
+                events,
+                termination,
+                t_span,
+                dt,
+                ics,
+            )
+        };
+    }
+
+    match solver {
+        OdeSolver::Explicit(ExplicitMethod::RALS3) => integrate!(Tableau(RALS3)),
+        OdeSolver::Explicit(ExplicitMethod::RK4) => integrate!(Tableau(RK4)),
+        OdeSolver::Explicit(ExplicitMethod::RALS4) => integrate!(Tableau(RALS4)),
+        OdeSolver::Explicit(ExplicitMethod::RK5) => integrate!(Tableau(RK5)),
+        OdeSolver::Embedded(EmbeddedMethod::BS23) => {
+            integrate!(Tableau(BS23::new(tol, sf, min, max, iter)))
+        }
+        OdeSolver::Embedded(EmbeddedMethod::RKF45) => {
+            integrate!(Tableau(RKF45::new(tol, sf, min, max, iter)))
+        }
+        OdeSolver::Embedded(EmbeddedMethod::DP45) => {
+            integrate!(Tableau(DP45::new(tol, sf, min, max, iter)))
+        }
+        OdeSolver::Embedded(EmbeddedMethod::TSIT45) => {
+            integrate!(Tableau(TSIT45::new(tol, sf, min, max, iter)))
+        }
+        OdeSolver::Implicit(ImplicitMethod::GL4) => {
+            integrate!(GaussLegendre4::new(parameters))
+        }
+        OdeSolver::Implicit(ImplicitMethod::ROS23) => {
+            integrate!(Rosenbrock23::new(parameters))
+        }
+        OdeSolver::Symplectic(method) => integrate!(Symplectic::new(method)),
+        OdeSolver::Auto => integrate!(AutoStepper::new(
+            DP45::new(tol, sf, min, max, iter),
+            EmbeddedMethod::DP45,
+            Rosenbrock23::new(parameters),
+        )),
+    }
+}
+
+/// Checks that the ODE is a separable Hamiltonian system, as the symplectic methods require.
+fn check_symplectic(settings: &OdeSettings) -> Result<()> {
+    let inputs = &settings.inputs;
+
+    if inputs.mode != InputMode::Hamiltonian {
+        anyhow::bail!("Symplectic methods need the ODE to be given by a Hamiltonian");
+    }
+
+    let hamiltonian = Atom::parse(&inputs.inputs[0])
+        .map_err(|e| anyhow::anyhow!("Failed to parse Hamiltonian: {}", e))?;
+
+    let coordinates = inputs
+        .variables
+        .iter()
+        .map(|q| settings.symbol(q))
+        .collect::<Vec<_>>();
+    let momenta = inputs
+        .conjugates
+        .iter()
+        .map(|p| settings.symbol(p))
+        .collect::<Vec<_>>();
+
+    if !is_separable(&hamiltonian, &coordinates, &momenta) {
+        anyhow::bail!("Symplectic methods need a separable Hamiltonian H = T(p) + V(q)");
+    }
+
+    Ok(())
+}
+
+/// Solves the ODE of `settings` from `ics` over `t_span` with the selected solver.
+///
+/// When a viewport is given, the integration stops once the solution leaves it
+/// by more than the margin of the termination settings.
+pub fn solve_ode(
+    settings: &OdeSettings,
+    t_span: (f64, f64),
+    ics: &[f64],
+    viewport: Option<Viewport>,
+) -> Result<Solution> {
+    let span = debug_span!(target: "metrics", "solve_ode");
+    let _enter = span.enter();
+
+    let solver = settings.ode_solver;
+    debug!(target: "metrics", ?t_span, ?solver, ics = ?ics.to_vec());
+
+    settings.parameters.validate()?;
+    if let OdeSolver::Symplectic(_) = solver {
+        check_symplectic(settings)?;
+    }
+
+    let mut problem = ExpressionODEProblem::create(settings)?;
+    if solver.needs_jacobian() {
+        problem = problem.with_jacobian(settings)?;
+    }
+
+    let events = EventFunctions::create(settings)?;
+    let termination = TerminationCheck::new(settings.termination, settings.projection(), viewport);
+
+    debug!(target: "metrics", "Solving ODE");
+    integrate_with(
+        solver,
+        &settings.parameters,
+        &problem,
+        events.as_ref(),
+        Some(&termination),
+        t_span,
+        ics,
+    )
+}
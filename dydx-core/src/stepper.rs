@@ -0,0 +1,304 @@
+use anyhow::{bail, Result};
+use peroxide::fuga::*;
+
+use super::{schemes::OdeSolver, solution::RejectedAttempt};
+
+/// An ODE problem that can also provide the Jacobian of its right-hand side.
+pub(crate) trait JacobianProblem: ODEProblem {
+    /// Writes ∂f/∂y into `jac` in row-major order and ∂f/∂t into `dfdt`.
+    fn jacobian(&self, t: f64, y: &[f64], jac: &mut [f64], dfdt: &mut [f64]) -> Result<()>;
+}
+
+/// The outcome of a single accepted step.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Step {
+    /// The step size that was actually taken
+    pub dt: f64,
+    /// The step size proposed for the next step
+    pub dt_next: f64,
+    /// The attempts rejected before this step was accepted
+    pub rejected: Vec<RejectedAttempt>,
+    /// The local error estimate of the step, for methods that provide one
+    pub error: Option<f64>,
+}
+
+/// A switch between methods made by a solver during the integration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MethodSwitch {
+    pub t: f64,
+    pub to: OdeSolver,
+}
+
+/// A single-step method driven by the solver loop.
+pub(crate) trait Stepper {
+    /// Advances `y` from `t`, attempting a step of size `dt` first.
+    ///
+    /// Fails with `ODEError::ReachedMaxStepIter` if no step could be accepted.
+    fn step<P: JacobianProblem>(&self, problem: &P, t: f64, y: &mut [f64], dt: f64)
+        -> Result<Step>;
+
+    /// The method switches made so far, for solvers that change method.
+    fn switches(&self) -> Vec<MethodSwitch> {
+        vec![]
+    }
+}
+
+/// The order of a Runge-Kutta method, or of the lower order solution of an
+/// embedded pair, whose local error estimate is then O(h^(q + 1)).
+pub(crate) trait Order {
+    const ORDER: i32;
+}
+
+macro_rules! impl_order {
+    ($($method:ty => $order:expr),* $(,)?) => {
+        $(impl Order for $method {
+            const ORDER: i32 = $order;
+        })*
+    };
+}
+
+impl_order! {
+    RALS3 => 3,
+    RK4 => 4,
+    RALS4 => 4,
+    RK5 => 5,
+    BS23 => 2,
+    RKF45 => 4,
+    DP45 => 4,
+    TSIT45 => 4,
+}
+
+/// Steps with an explicit or embedded Runge-Kutta method given by its Butcher tableau.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Tableau<B: ButcherTableau + Order>(pub B);
+
+impl<B: ButcherTableau + Order> Tableau<B> {
+    /// Evaluates the stages of the method for a step of size `dt`.
+    pub fn stages<P: ODEProblem>(
+        &self,
+        problem: &P,
+        t: f64,
+        y: &[f64],
+        dt: f64,
+    ) -> Result<Vec<Vec<f64>>> {
+        let n = y.len();
+        let mut k = vec![vec![0.0; n]; B::C.len()];
+        let mut y_stage = y.to_vec();
+
+        for stage in 0..B::C.len() {
+            for i in 0..n {
+                let s = (0..stage).map(|j| B::A[stage][j] * k[j][i]).sum::<f64>();
+                y_stage[i] = y[i] + dt * s;
+            }
+
+            problem.rhs(t + dt * B::C[stage], &y_stage, &mut k[stage])?;
+        }
+
+        Ok(k)
+    }
+
+    /// The error estimate of the embedded pair, or `None` for an explicit method.
+    fn error(&self, k: &[Vec<f64>], dt: f64) -> Option<f64> {
+        if B::BL.is_empty() {
+            return None;
+        }
+
+        let n = k.first().map_or(0, |k| k.len());
+        let error = (0..n)
+            .map(|i| {
+                let s = (0..k.len())
+                    .map(|j| (B::BH[j] - B::BL[j]) * k[j][i])
+                    .sum::<f64>();
+                (dt * s).abs()
+            })
+            .fold(0.0, f64::max);
+
+        Some(error)
+    }
+}
+
+impl<B: ButcherTableau + Order> Stepper for Tableau<B> {
+    fn step<P: JacobianProblem>(
+        &self,
+        problem: &P,
+        t: f64,
+        y: &mut [f64],
+        dt: f64,
+    ) -> Result<Step> {
+        let mut dt = dt;
+        let mut rejected = vec![];
+
+        loop {
+            let k = self.stages(problem, t, y, dt)?;
+            let error = self.error(&k, dt);
+
+            let dt_next = match error {
+                Some(error) => {
+                    // The error is that of the step, as in the acceptance test below.
+                    let exponent = 1.0 / (B::ORDER + 1) as f64;
+                    let factor = (self.0.tol() / error).powf(exponent);
+                    (self.0.safety_factor() * dt * factor)
+                        .clamp(self.0.min_step_size(), self.0.max_step_size())
+                }
+                None => dt,
+            };
+
+            if error.map_or(true, |error| error < self.0.tol()) {
+                for (i, y) in y.iter_mut().enumerate() {
+                    *y += dt * (0..k.len()).map(|j| B::BH[j] * k[j][i]).sum::<f64>();
+                }
+
+                return Ok(Step {
+                    dt,
+                    dt_next,
+                    rejected,
+                    error,
+                });
+            }
+
+            rejected.push(RejectedAttempt { dt, error });
+            if rejected.len() >= self.0.max_step_iter() {
+                bail!(ODEError::ReachedMaxStepIter);
+            }
+
+            dt = dt_next;
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// The ODE y' = -y, with y(t) = exp(-t) from y(0) = 1.
+    pub(crate) struct Decay;
+
+    impl ODEProblem for Decay {
+        fn rhs(&self, _t: f64, y: &[f64], dy: &mut [f64]) -> Result<()> {
+            dy[0] = -y[0];
+            Ok(())
+        }
+    }
+
+    impl JacobianProblem for Decay {
+        fn jacobian(&self, _t: f64, _y: &[f64], jac: &mut [f64], dfdt: &mut [f64]) -> Result<()> {
+            jac[0] = -1.0;
+            dfdt[0] = 0.0;
+            Ok(())
+        }
+    }
+
+    /// The error at t = 1 of `Decay` after `steps` steps of equal size.
+    fn decay_error<S: Stepper>(stepper: &S, steps: usize) -> f64 {
+        let dt = 1.0 / steps as f64;
+        let mut t = 0.0;
+        let mut y = [1.0];
+
+        for _ in 0..steps {
+            let step = stepper.step(&Decay, t, &mut y, dt).unwrap();
+            assert_eq!(step.dt, dt);
+            t += dt;
+        }
+
+        (y[0] - (-1.0f64).exp()).abs()
+    }
+
+    /// The order of convergence observed when halving the step size.
+    pub(crate) fn observed_order<S: Stepper>(stepper: &S) -> f64 {
+        (decay_error(stepper, 10) / decay_error(stepper, 20)).log2()
+    }
+
+    /// Embedded pairs with an infinite tolerance, so that every step is accepted.
+    fn embedded<B>(new: impl Fn(f64, f64, f64, f64, usize) -> B) -> Tableau<B>
+    where
+        B: ButcherTableau + Order,
+    {
+        Tableau(new(f64::INFINITY, 0.9, 0.0, 1.0, 10))
+    }
+
+    /// Checks the observed order against the expected one, from below only as
+    /// linear problems can converge faster than the method's order.
+    fn assert_order<S: Stepper>(stepper: &S, order: i32) {
+        let observed = observed_order(stepper);
+        assert!(
+            observed > order as f64 - 0.3,
+            "Expected order {}, observed {}",
+            order,
+            observed
+        );
+    }
+
+    #[test]
+    fn explicit_methods_converge_at_their_order() {
+        assert_order(&Tableau(RALS3), RALS3::ORDER);
+        assert_order(&Tableau(RK4), RK4::ORDER);
+        assert_order(&Tableau(RALS4), RALS4::ORDER);
+        assert_order(&Tableau(RK5), RK5::ORDER);
+    }
+
+    #[test]
+    fn embedded_pairs_propagate_the_higher_order_solution() {
+        assert_order(&embedded(BS23::new), BS23::ORDER + 1);
+        assert_order(&embedded(RKF45::new), RKF45::ORDER + 1);
+        assert_order(&embedded(DP45::new), DP45::ORDER + 1);
+        assert_order(&embedded(TSIT45::new), TSIT45::ORDER + 1);
+    }
+
+    #[test]
+    fn embedded_error_estimates_scale_with_the_lower_order() {
+        // The local error estimate of a pair of orders q and q + 1 is O(h^(q + 1)).
+        fn estimate<B: ButcherTableau + Order>(tableau: &Tableau<B>, dt: f64) -> f64 {
+            let mut y = [1.0];
+            tableau
+                .step(&Decay, 0.0, &mut y, dt)
+                .unwrap()
+                .error
+                .unwrap()
+        }
+
+        fn assert_estimate_order<B: ButcherTableau + Order>(tableau: Tableau<B>) {
+            let observed = (estimate(&tableau, 0.1) / estimate(&tableau, 0.05)).log2();
+            let expected = (B::ORDER + 1) as f64;
+            assert!(
+                observed > expected - 0.3,
+                "Expected error order {}, observed {}",
+                expected,
+                observed
+            );
+        }
+
+        assert_estimate_order(embedded(BS23::new));
+        assert_estimate_order(embedded(RKF45::new));
+        assert_estimate_order(embedded(DP45::new));
+        assert_estimate_order(embedded(TSIT45::new));
+    }
+
+    #[test]
+    fn accepted_steps_grow_on_a_smooth_problem() {
+        let dt = 0.1;
+        let error = embedded(DP45::new)
+            .step(&Decay, 0.0, &mut [1.0], dt)
+            .unwrap()
+            .error
+            .unwrap();
+
+        // Half the tolerance is left over, so the next step should be larger.
+        let tableau = Tableau(DP45::new(2.0 * error, 0.9, 1e-6, 1.0, 10));
+        let step = tableau.step(&Decay, 0.0, &mut [1.0], dt).unwrap();
+
+        assert!(step.rejected.is_empty());
+        assert!(step.dt_next > dt, "The step shrank to {}", step.dt_next);
+    }
+
+    #[test]
+    fn rejected_steps_are_retried_with_a_smaller_step() {
+        let tableau = Tableau(DP45::new(1e-8, 0.9, 1e-6, 1.0, 50));
+        let mut y = [1.0];
+        let step = tableau.step(&Decay, 0.0, &mut y, 1.0).unwrap();
+
+        assert!(!step.rejected.is_empty());
+        assert!(step.dt < 1.0);
+        assert!(step.error.unwrap() < 1e-8);
+        assert!((y[0] - (-step.dt).exp()).abs() < 1e-8);
+    }
+}
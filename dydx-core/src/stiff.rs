@@ -0,0 +1,613 @@
+use std::cell::{Cell, RefCell};
+
+use anyhow::{bail, Result};
+use peroxide::fuga::*;
+use tracing::debug;
+
+use super::{
+    parameters::SolverParameters,
+    schemes::{EmbeddedMethod, ImplicitMethod, OdeSolver},
+    solution::RejectedAttempt,
+    stepper::{JacobianProblem, MethodSwitch, Order, Step, Stepper, Tableau},
+};
+
+/// The linearly implicit Rosenbrock 2(3) method of Shampine and Reichelt (`ode23s`).
+///
+/// Each step solves linear systems with the matrix `I - h d J`, so the method
+/// stays stable for stiff problems without any nonlinear iteration.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Rosenbrock23 {
+    tol: f64,
+    safety_factor: f64,
+    min_step_size: f64,
+    max_step_size: f64,
+    max_step_iter: usize,
+}
+
+impl Rosenbrock23 {
+    const D: f64 = 1.0 / (2.0 + std::f64::consts::SQRT_2);
+    const E32: f64 = 6.0 + std::f64::consts::SQRT_2;
+
+    pub fn new(parameters: &SolverParameters) -> Self {
+        Self {
+            tol: parameters.tolerance.0,
+            safety_factor: parameters.safety_factor.0,
+            min_step_size: parameters.min_step_size.0,
+            max_step_size: parameters.max_step_size.0,
+            max_step_iter: parameters.max_steps.0,
+        }
+    }
+
+    /// Attempts a single step of size `dt`, returning the new state and its error estimate.
+    fn attempt<P: JacobianProblem>(
+        &self,
+        problem: &P,
+        t: f64,
+        y: &[f64],
+        dt: f64,
+        (jac, dfdt): (&[f64], &[f64]),
+        f0: &[f64],
+    ) -> Result<(Vec<f64>, f64)> {
+        let n = y.len();
+        let d = Self::D;
+
+        let mut w = vec![0.0; n * n];
+        for i in 0..n {
+            for j in 0..n {
+                w[i * n + j] = if i == j { 1.0 } else { 0.0 } - dt * d * jac[i * n + j];
+            }
+        }
+        let lu = Lu::decompose(w, n)?;
+
+        let rhs = (0..n).map(|i| f0[i] + dt * d * dfdt[i]).collect::<Vec<_>>();
+        let k1 = lu.solve(&rhs);
+
+        let y1 = (0..n).map(|i| y[i] + 0.5 * dt * k1[i]).collect::<Vec<_>>();
+        let mut f1 = vec![0.0; n];
+        problem.rhs(t + 0.5 * dt, &y1, &mut f1)?;
+
+        let rhs = (0..n).map(|i| f1[i] - k1[i]).collect::<Vec<_>>();
+        let k2 = lu
+            .solve(&rhs)
+            .iter()
+            .zip(&k1)
+            .map(|(k, k1)| k + k1)
+            .collect::<Vec<_>>();
+
+        let y_new = (0..n).map(|i| y[i] + dt * k2[i]).collect::<Vec<_>>();
+        let mut f2 = vec![0.0; n];
+        problem.rhs(t + dt, &y_new, &mut f2)?;
+
+        let rhs = (0..n)
+            .map(|i| f2[i] - Self::E32 * (k2[i] - f1[i]) - 2.0 * (k1[i] - f0[i]) + dt * d * dfdt[i])
+            .collect::<Vec<_>>();
+        let k3 = lu.solve(&rhs);
+
+        let error = (0..n)
+            .map(|i| (dt / 6.0 * (k1[i] - 2.0 * k2[i] + k3[i])).abs())
+            .fold(0.0, f64::max);
+
+        Ok((y_new, error))
+    }
+}
+
+impl Stepper for Rosenbrock23 {
+    fn step<P: JacobianProblem>(
+        &self,
+        problem: &P,
+        t: f64,
+        y: &mut [f64],
+        dt: f64,
+    ) -> Result<Step> {
+        let n = y.len();
+        let mut jac = vec![0.0; n * n];
+        let mut dfdt = vec![0.0; n];
+        let mut f0 = vec![0.0; n];
+
+        problem.jacobian(t, y, &mut jac, &mut dfdt)?;
+        problem.rhs(t, y, &mut f0)?;
+
+        let mut dt = dt;
+        let mut rejected = vec![];
+
+        loop {
+            let (y_new, error) = self.attempt(problem, t, y, dt, (&jac, &dfdt), &f0)?;
+
+            let factor = (self.tol / error).powf(1.0 / 3.0).min(5.0);
+            let dt_next =
+                (self.safety_factor * dt * factor).clamp(self.min_step_size, self.max_step_size);
+
+            if error < self.tol && y_new.iter().all(|y| y.is_finite()) {
+                y.copy_from_slice(&y_new);
+
+                return Ok(Step {
+                    dt,
+                    dt_next,
+                    rejected,
+                    error: Some(error),
+                });
+            }
+
+            rejected.push(RejectedAttempt {
+                dt,
+                error: Some(error),
+            });
+            if rejected.len() >= self.max_step_iter {
+                bail!(ODEError::ReachedMaxStepIter);
+            }
+
+            dt = if dt_next.is_finite() {
+                dt_next
+            } else {
+                0.5 * dt
+            };
+        }
+    }
+}
+
+/// The two-stage Gauss-Legendre method of order 4, with fixed steps.
+///
+/// The stage equations are solved by a simplified Newton iteration with the
+/// matrix `I - h A ⊗ J`, where J is the Jacobian at the start of the step, which
+/// converges for stiff problems where a fixed point iteration diverges. A step
+/// whose iteration does not converge is retried with half the step size.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GaussLegendre4 {
+    tol: f64,
+    max_step_iter: usize,
+}
+
+impl GaussLegendre4 {
+    /// √3 / 6
+    const S: f64 = 0.288_675_134_594_812_9;
+    const A: [[f64; 2]; 2] = [[0.25, 0.25 - Self::S], [0.25 + Self::S, 0.25]];
+    const C: [f64; 2] = [0.5 - Self::S, 0.5 + Self::S];
+
+    pub fn new(parameters: &SolverParameters) -> Self {
+        Self {
+            tol: parameters.tolerance.0,
+            max_step_iter: parameters.max_steps.0,
+        }
+    }
+
+    /// Evaluates the right-hand side at both stages for the stage increments `z`.
+    fn stages<P: JacobianProblem>(
+        problem: &P,
+        t: f64,
+        y: &[f64],
+        dt: f64,
+        z: &[f64],
+        f: &mut [f64],
+    ) -> Result<()> {
+        let n = y.len();
+
+        for (s, c) in Self::C.iter().enumerate() {
+            let ys = (0..n).map(|i| y[i] + z[s * n + i]).collect::<Vec<_>>();
+            problem.rhs(t + c * dt, &ys, &mut f[s * n..(s + 1) * n])?;
+        }
+
+        Ok(())
+    }
+
+    /// Attempts a single step of size `dt`, returning the new state if the
+    /// Newton iteration converged.
+    fn attempt<P: JacobianProblem>(
+        &self,
+        problem: &P,
+        t: f64,
+        y: &[f64],
+        dt: f64,
+        jac: &[f64],
+    ) -> Result<Option<Vec<f64>>> {
+        let n = y.len();
+        let m = 2 * n;
+
+        let mut w = vec![0.0; m * m];
+        for (si, row) in Self::A.iter().enumerate() {
+            for (sj, a) in row.iter().enumerate() {
+                for i in 0..n {
+                    for j in 0..n {
+                        let identity = if si == sj && i == j { 1.0 } else { 0.0 };
+                        w[(si * n + i) * m + sj * n + j] = identity - dt * a * jac[i * n + j];
+                    }
+                }
+            }
+        }
+        let Ok(lu) = Lu::decompose(w, m) else {
+            return Ok(None);
+        };
+
+        let mut z = vec![0.0; m];
+        let mut f = vec![0.0; m];
+
+        for _ in 0..self.max_step_iter {
+            Self::stages(problem, t, y, dt, &z, &mut f)?;
+
+            let residual = (0..m)
+                .map(|k| {
+                    let (s, i) = (k / n, k % n);
+                    dt * (Self::A[s][0] * f[i] + Self::A[s][1] * f[n + i]) - z[k]
+                })
+                .collect::<Vec<_>>();
+            let delta = lu.solve(&residual);
+
+            if !delta.iter().all(|d| d.is_finite()) {
+                return Ok(None);
+            }
+
+            let mut norm = 0.0;
+            for (k, (z, d)) in z.iter_mut().zip(&delta).enumerate() {
+                *z += d;
+                norm = f64::max(norm, d.abs() / (1.0 + y[k % n].abs()));
+            }
+
+            if norm <= self.tol {
+                Self::stages(problem, t, y, dt, &z, &mut f)?;
+                let y_new = (0..n)
+                    .map(|i| y[i] + 0.5 * dt * (f[i] + f[n + i]))
+                    .collect::<Vec<_>>();
+
+                return Ok(Some(y_new));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl Stepper for GaussLegendre4 {
+    fn step<P: JacobianProblem>(
+        &self,
+        problem: &P,
+        t: f64,
+        y: &mut [f64],
+        dt: f64,
+    ) -> Result<Step> {
+        let n = y.len();
+        let mut jac = vec![0.0; n * n];
+        let mut dfdt = vec![0.0; n];
+        problem.jacobian(t, y, &mut jac, &mut dfdt)?;
+
+        let mut h = dt;
+        let mut rejected = vec![];
+
+        loop {
+            if let Some(y_new) = self.attempt(problem, t, y, h, &jac)? {
+                if y_new.iter().all(|y| y.is_finite()) {
+                    y.copy_from_slice(&y_new);
+
+                    return Ok(Step {
+                        dt: h,
+                        dt_next: dt,
+                        rejected,
+                        error: None,
+                    });
+                }
+            }
+
+            rejected.push(RejectedAttempt { dt: h, error: None });
+            if rejected.len() >= self.max_step_iter {
+                bail!(ODEError::ReachedMaxStepIter);
+            }
+
+            h *= 0.5;
+        }
+    }
+}
+
+/// The number of recent steps considered when deciding whether a problem is stiff.
+const STIFFNESS_WINDOW: usize = 10;
+
+/// Steps with an explicit pair until the problem looks stiff, then with `Rosenbrock23`.
+///
+/// The problem is considered stiff when steps keep being rejected while the step size
+/// sits at the stability boundary of the explicit method, h‖J‖ ≳ 3. Once the stiff
+/// method's steps are well inside the explicit stability region again, it switches back.
+pub(crate) struct AutoStepper<B: ButcherTableau + Order> {
+    explicit: Tableau<B>,
+    explicit_method: OdeSolver,
+    implicit: Rosenbrock23,
+    stiff: Cell<bool>,
+    /// Rejections of the most recent steps
+    history: RefCell<Vec<usize>>,
+    switches: RefCell<Vec<MethodSwitch>>,
+}
+
+impl<B: ButcherTableau + Order> AutoStepper<B> {
+    /// The value of h‖J‖ above which the explicit method is limited by stability.
+    const STABILITY_BOUND: f64 = 3.0;
+
+    pub fn new(explicit: B, explicit_method: EmbeddedMethod, implicit: Rosenbrock23) -> Self {
+        Self {
+            explicit: Tableau(explicit),
+            explicit_method: OdeSolver::Embedded(explicit_method),
+            implicit,
+            stiff: Cell::new(false),
+            history: RefCell::new(vec![]),
+            switches: RefCell::new(vec![]),
+        }
+    }
+
+    fn switch(&self, t: f64, stiff: bool) {
+        let to = if stiff {
+            OdeSolver::Implicit(ImplicitMethod::ROS23)
+        } else {
+            self.explicit_method
+        };
+
+        debug!(target: "metrics", t, ?to, "Switching method");
+        self.stiff.set(stiff);
+        self.history.borrow_mut().clear();
+        self.switches.borrow_mut().push(MethodSwitch { t, to });
+    }
+}
+
+impl<B: ButcherTableau + Order> Stepper for AutoStepper<B> {
+    fn step<P: JacobianProblem>(
+        &self,
+        problem: &P,
+        t: f64,
+        y: &mut [f64],
+        dt: f64,
+    ) -> Result<Step> {
+        let n = y.len();
+        let mut jac = vec![0.0; n * n];
+        let mut dfdt = vec![0.0; n];
+        problem.jacobian(t, y, &mut jac, &mut dfdt)?;
+
+        // The infinity norm bounds the spectral radius of the Jacobian.
+        let norm = (0..n)
+            .map(|i| (0..n).map(|j| jac[i * n + j].abs()).sum::<f64>())
+            .fold(0.0, f64::max);
+
+        let step = if self.stiff.get() {
+            self.implicit.step(problem, t, y, dt)?
+        } else {
+            self.explicit.step(problem, t, y, dt)?
+        };
+
+        let mut history = self.history.borrow_mut();
+        history.push(step.rejected.len());
+        if history.len() > STIFFNESS_WINDOW {
+            history.remove(0);
+        }
+
+        let full = history.len() == STIFFNESS_WINDOW;
+        let rejections = history.iter().sum::<usize>();
+        let h_norm = step.dt * norm;
+        drop(history);
+
+        if !self.stiff.get() && full && rejections >= 3 && h_norm >= 0.5 * Self::STABILITY_BOUND {
+            self.switch(t + step.dt, true);
+        } else if self.stiff.get() && full && step.dt_next * norm < 0.2 * Self::STABILITY_BOUND {
+            self.switch(t + step.dt, false);
+        }
+
+        Ok(step)
+    }
+
+    fn switches(&self) -> Vec<MethodSwitch> {
+        self.switches.borrow().clone()
+    }
+}
+
+/// An LU decomposition with partial pivoting of a dense square matrix.
+struct Lu {
+    n: usize,
+    lu: Vec<f64>,
+    pivots: Vec<usize>,
+}
+
+impl Lu {
+    /// Decomposes the row-major `n` by `n` matrix `a`.
+    fn decompose(mut a: Vec<f64>, n: usize) -> Result<Self> {
+        let mut pivots = (0..n).collect::<Vec<_>>();
+
+        for col in 0..n {
+            let pivot = (col..n)
+                .max_by(|&i, &j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))
+                .unwrap_or(col);
+
+            if a[pivot * n + col] == 0.0 || !a[pivot * n + col].is_finite() {
+                bail!("Singular iteration matrix");
+            }
+
+            if pivot != col {
+                for j in 0..n {
+                    a.swap(pivot * n + j, col * n + j);
+                }
+                pivots.swap(pivot, col);
+            }
+
+            for row in col + 1..n {
+                let factor = a[row * n + col] / a[col * n + col];
+                a[row * n + col] = factor;
+
+                for j in col + 1..n {
+                    a[row * n + j] -= factor * a[col * n + j];
+                }
+            }
+        }
+
+        Ok(Self { n, lu: a, pivots })
+    }
+
+    fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let n = self.n;
+        let mut x = self.pivots.iter().map(|&p| b[p]).collect::<Vec<_>>();
+
+        for i in 0..n {
+            for j in 0..i {
+                x[i] -= self.lu[i * n + j] * x[j];
+            }
+        }
+
+        for i in (0..n).rev() {
+            for j in i + 1..n {
+                x[i] -= self.lu[i * n + j] * x[j];
+            }
+            x[i] /= self.lu[i * n + i];
+        }
+
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parameters::{MaxStepSize, MinStepSize, Tolerance},
+        settings::{OdeInputs, OdeSettings},
+        solver::solve_ode,
+        stepper::tests::observed_order,
+        termination::Termination,
+    };
+
+    /// Parameters under which every step is accepted at the size it is attempted.
+    fn accept_all() -> SolverParameters {
+        SolverParameters {
+            tolerance: Tolerance(f64::INFINITY),
+            min_step_size: MinStepSize(0.0),
+            max_step_size: MaxStepSize(1.0),
+            ..Default::default()
+        }
+    }
+
+    /// The stiff ODE y' = -1000 (y - cos t), whose solution quickly settles onto
+    /// a curve close to cos t.
+    struct Relaxation;
+
+    impl ODEProblem for Relaxation {
+        fn rhs(&self, t: f64, y: &[f64], dy: &mut [f64]) -> Result<()> {
+            dy[0] = -1000.0 * (y[0] - t.cos());
+            Ok(())
+        }
+    }
+
+    impl JacobianProblem for Relaxation {
+        fn jacobian(&self, t: f64, _y: &[f64], jac: &mut [f64], dfdt: &mut [f64]) -> Result<()> {
+            jac[0] = -1000.0;
+            dfdt[0] = -1000.0 * t.sin();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn lu_solves_with_pivoting() {
+        let a = vec![2.0, 1.0, 1.0, 4.0, -6.0, 0.0, -2.0, 7.0, 2.0];
+        let lu = Lu::decompose(a, 3).unwrap();
+        let x = lu.solve(&[5.0, -2.0, 9.0]);
+
+        for (x, expected) in x.iter().zip([1.0, 1.0, 2.0]) {
+            assert!((x - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn lu_rejects_singular_matrices() {
+        assert!(Lu::decompose(vec![1.0, 2.0, 2.0, 4.0], 2).is_err());
+    }
+
+    #[test]
+    fn rosenbrock_is_second_order() {
+        let observed = observed_order(&Rosenbrock23::new(&accept_all()));
+        assert!(observed > 1.7, "Observed order {}", observed);
+    }
+
+    #[test]
+    fn gauss_legendre_is_fourth_order() {
+        let parameters = SolverParameters {
+            tolerance: Tolerance(1e-12),
+            ..accept_all()
+        };
+        let observed = observed_order(&GaussLegendre4::new(&parameters));
+        assert!(observed > 3.7, "Observed order {}", observed);
+    }
+
+    #[test]
+    fn gauss_legendre_is_stable_for_stiff_problems() {
+        let parameters = SolverParameters {
+            tolerance: Tolerance(1e-10),
+            ..accept_all()
+        };
+        let stepper = GaussLegendre4::new(&parameters);
+        let (mut t, mut y) = (0.0, [1.0]);
+
+        // Steps far beyond the explicit stability limit of h = 0.003.
+        for _ in 0..20 {
+            let step = stepper.step(&Relaxation, t, &mut y, 0.1).unwrap();
+            t += step.dt;
+        }
+
+        assert!((y[0] - t.cos()).abs() < 1e-2, "y({}) = {}", t, y[0]);
+    }
+
+    #[test]
+    fn auto_switches_to_rosenbrock_when_stiff() {
+        let parameters = SolverParameters {
+            tolerance: Tolerance(1e-3),
+            min_step_size: MinStepSize(1e-10),
+            max_step_size: MaxStepSize(0.5),
+            ..Default::default()
+        };
+        let stepper = AutoStepper::new(
+            DP45::new(1e-3, 0.9, 1e-10, 0.5, 1000),
+            EmbeddedMethod::DP45,
+            Rosenbrock23::new(&parameters),
+        );
+
+        let (mut t, mut y, mut dt) = (0.0, [1.0], 1e-3);
+        while t < 10.0 {
+            let step = stepper.step(&Relaxation, t, &mut y, dt).unwrap();
+            t += step.dt;
+            dt = step.dt_next;
+        }
+
+        let switches = stepper.switches();
+        assert!(!switches.is_empty());
+        assert_eq!(switches[0].to, OdeSolver::Implicit(ImplicitMethod::ROS23));
+        assert!((y[0] - t.cos()).abs() < 1e-2, "y({}) = {}", t, y[0]);
+    }
+
+    /// Robertson's chemical kinetics, a classic stiff test problem.
+    fn robertson(solver: OdeSolver) -> Vec<f64> {
+        let mut settings = OdeSettings::default();
+        settings.inputs = OdeInputs::system(
+            &["x", "y", "z"],
+            &[
+                "-0.04*x + 10000*y*z",
+                "0.04*x - 10000*y*z - 30000000*y^2",
+                "30000000*y^2",
+            ],
+        );
+        settings.update_inputs();
+        settings.ode_solver = solver;
+        settings.parameters = SolverParameters {
+            tolerance: Tolerance(1e-6),
+            min_step_size: MinStepSize(1e-9),
+            max_step_size: MaxStepSize(1.0),
+            initial_step_size: 1e-6,
+            ..Default::default()
+        };
+
+        let solution = solve_ode(&settings, (0.0, 40.0), &[1.0, 0.0, 0.0], None).unwrap();
+        assert_eq!(solution.termination, Termination::Completed);
+        solution.eval(40.0).unwrap()
+    }
+
+    #[test]
+    fn stiff_solvers_solve_robertson() {
+        for solver in [OdeSolver::Implicit(ImplicitMethod::ROS23), OdeSolver::Auto] {
+            let y = robertson(solver);
+
+            assert!(
+                (y[0] - 0.7158).abs() < 1e-3,
+                "{:?}: x(40) = {}",
+                solver,
+                y[0]
+            );
+            assert!((y.iter().sum::<f64>() - 1.0).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+
+use super::{
+    schemes::SymplecticMethod,
+    stepper::{JacobianProblem, Step, Stepper},
+};
+
+/// Steps a separable Hamiltonian system with a symplectic composition of leapfrog steps.
+///
+/// The state is expected to hold the coordinates followed by their momenta.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Symplectic {
+    method: SymplecticMethod,
+}
+
+impl Symplectic {
+    pub fn new(method: SymplecticMethod) -> Self {
+        Self { method }
+    }
+
+    /// The fractions of the step taken by each leapfrog substep.
+    fn weights(&self) -> Vec<f64> {
+        match self.method {
+            SymplecticMethod::Verlet => vec![1.0],
+            SymplecticMethod::Yoshida4 => {
+                let cbrt2 = 2f64.cbrt();
+                let w1 = 1.0 / (2.0 - cbrt2);
+                let w0 = -cbrt2 / (2.0 - cbrt2);
+                vec![w1, w0, w1]
+            }
+        }
+    }
+
+    /// A kick-drift-kick Störmer-Verlet step of size `h`.
+    fn leapfrog<P: JacobianProblem>(
+        &self,
+        problem: &P,
+        t: f64,
+        y: &mut [f64],
+        h: f64,
+    ) -> Result<()> {
+        let n = y.len() / 2;
+        let mut dy = vec![0.0; y.len()];
+
+        problem.rhs(t, y, &mut dy)?;
+        for i in n..2 * n {
+            y[i] += 0.5 * h * dy[i];
+        }
+
+        problem.rhs(t + 0.5 * h, y, &mut dy)?;
+        for i in 0..n {
+            y[i] += h * dy[i];
+        }
+
+        problem.rhs(t + h, y, &mut dy)?;
+        for i in n..2 * n {
+            y[i] += 0.5 * h * dy[i];
+        }
+
+        Ok(())
+    }
+}
+
+impl Stepper for Symplectic {
+    fn step<P: JacobianProblem>(
+        &self,
+        problem: &P,
+        t: f64,
+        y: &mut [f64],
+        dt: f64,
+    ) -> Result<Step> {
+        if y.len() % 2 != 0 {
+            anyhow::bail!("Symplectic methods need a state of coordinates and momenta");
+        }
+
+        let mut t_sub = t;
+        for w in self.weights() {
+            self.leapfrog(problem, t_sub, y, w * dt)?;
+            t_sub += w * dt;
+        }
+
+        Ok(Step {
+            dt,
+            dt_next: dt,
+            rejected: vec![],
+            error: None,
+        })
+    }
+}
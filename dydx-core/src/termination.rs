@@ -0,0 +1,161 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::settings::Projection;
+
+/// Why the integration of an ODE stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Termination {
+    /// The end of the integration span was reached
+    Completed,
+    /// A terminal event was hit
+    Event(usize),
+    /// The state became NaN or infinite
+    NonFinite,
+    /// The norm of the state exceeded the configured bound
+    BoundExceeded,
+    /// The solution left the viewport and its margin
+    LeftViewport,
+    /// The step size fell below the configured minimum
+    StepSizeUnderflow,
+    /// The integrator could not satisfy the tolerance within its maximum number of iterations
+    MaxStepIterations,
+}
+
+impl Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Termination::Completed => write!(f, "Reached the end of the integration span"),
+            Termination::Event(i) => write!(f, "Stopped at terminal event {}", i),
+            Termination::NonFinite => write!(f, "Solution blew up (non-finite state)"),
+            Termination::BoundExceeded => write!(f, "Solution exceeded the state bound"),
+            Termination::LeftViewport => write!(f, "Solution left the viewport"),
+            Termination::StepSizeUnderflow => write!(f, "Step size underflow"),
+            Termination::MaxStepIterations => {
+                write!(f, "Reached the maximum number of step iterations")
+            }
+        }
+    }
+}
+
+/// The region of the plane that is visible, in plot coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub x_min: f64,
+    pub x_max: f64,
+    pub y_min: f64,
+    pub y_max: f64,
+}
+
+/// Conditions that stop the integration before the end of the span.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminationSettings {
+    pub stop_non_finite: bool,
+    /// Stop once the largest component of the state exceeds this bound.
+    pub max_norm: Option<f64>,
+    pub stop_outside_viewport: bool,
+    /// The margin around the viewport, as a fraction of its size.
+    pub viewport_margin: f64,
+    /// Stop once the proposed step size falls below this. It should be under the
+    /// minimum step size of the solver, which steps are clamped to.
+    pub min_step_size: f64,
+}
+
+impl Default for TerminationSettings {
+    fn default() -> Self {
+        Self {
+            stop_non_finite: true,
+            max_norm: Some(1e8),
+            stop_outside_viewport: true,
+            viewport_margin: 0.5,
+            min_step_size: 1e-9,
+        }
+    }
+}
+
+/// The termination conditions of a single solve.
+pub(crate) struct TerminationCheck {
+    settings: TerminationSettings,
+    projection: Projection,
+    viewport: Option<Viewport>,
+}
+
+impl TerminationCheck {
+    pub fn new(
+        settings: TerminationSettings,
+        projection: Projection,
+        viewport: Option<Viewport>,
+    ) -> Self {
+        let viewport = viewport
+            .filter(|_| settings.stop_outside_viewport)
+            .map(|v| {
+                let dx = settings.viewport_margin * (v.x_max - v.x_min);
+                let dy = settings.viewport_margin * (v.y_max - v.y_min);
+
+                Viewport {
+                    x_min: v.x_min - dx,
+                    x_max: v.x_max + dx,
+                    y_min: v.y_min - dy,
+                    y_max: v.y_max + dy,
+                }
+            });
+
+        Self {
+            settings,
+            projection,
+            viewport,
+        }
+    }
+
+    /// Checks the state `y` at `t`, returning the reason to stop if any.
+    pub fn check_state(&self, t: f64, y: &[f64]) -> Option<Termination> {
+        if self.settings.stop_non_finite && !(t.is_finite() && y.iter().all(|v| v.is_finite())) {
+            return Some(Termination::NonFinite);
+        }
+
+        if let Some(bound) = self.settings.max_norm {
+            if y.iter().any(|v| v.abs() > bound) {
+                return Some(Termination::BoundExceeded);
+            }
+        }
+
+        if let Some(viewport) = &self.viewport {
+            let (x, y) = self.projection.project(t, y);
+            let inside = (viewport.x_min..=viewport.x_max).contains(&x)
+                && (viewport.y_min..=viewport.y_max).contains(&y);
+
+            if !inside {
+                return Some(Termination::LeftViewport);
+            }
+        }
+
+        None
+    }
+
+    /// Checks the step size proposed by the integrator.
+    ///
+    /// The comparison is strict, so that steps held at a minimum step size equal
+    /// to the threshold keep going.
+    pub fn check_step(&self, dt: f64) -> Option<Termination> {
+        (dt.abs() < self.settings.min_step_size).then_some(Termination::StepSizeUnderflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_size_underflow_is_strict() {
+        let settings = TerminationSettings {
+            min_step_size: 1e-6,
+            ..Default::default()
+        };
+        let check = TerminationCheck::new(settings, Projection::Graph, None);
+
+        assert_eq!(check.check_step(1e-6), None);
+        assert_eq!(check.check_step(5e-7), Some(Termination::StepSizeUnderflow));
+    }
+}
@@ -1,9 +1,145 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
 use clap_verbosity_flag::{ErrorLevel, Verbosity};
+use dydx_core::OdeSolver;
 
 #[derive(Debug, Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, allow_negative_numbers = true)]
 pub struct Cli {
     #[command(flatten)]
     pub verbose: Verbosity<ErrorLevel>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// A session file to start from, which the other options override
+    #[arg(long, value_name = "FILE", global = true)]
+    pub session: Option<PathBuf>,
+
+    /// A preset to start from by name, e.g. lorenz, which the other options override
+    #[arg(long, value_name = "NAME", global = true, conflicts_with = "session")]
+    pub preset: Option<String>,
+
+    /// The right-hand side of a scalar ODE y' = f(x, y), or one equation of a
+    /// system per occurrence, in the order of --variables
+    #[arg(short, long = "equation", value_name = "EXPR", global = true)]
+    pub equations: Vec<String>,
+
+    /// The state variables of a system, one per equation
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', global = true)]
+    pub variables: Vec<String>,
+
+    /// The initial conditions: x₀,y₀ for a scalar ODE, or the initial state of a system
+    #[arg(long, value_name = "VALUES", value_delimiter = ',', global = true)]
+    pub ics: Vec<f64>,
+
+    /// The length of the integration span
+    #[arg(long, value_name = "LENGTH", value_parser = positive, global = true)]
+    pub length: Option<f64>,
+
+    /// The solver, by name, e.g. rk4 or dormand-prince-45
+    #[arg(long, global = true)]
+    pub solver: Option<OdeSolver>,
+
+    /// The error tolerance of the adaptive solvers
+    #[arg(long, value_parser = positive, global = true)]
+    pub tolerance: Option<f64>,
+
+    /// Solve a scalar ODE in polar coordinates θ' = f(r, θ)
+    #[arg(long, global = true)]
+    pub polar: bool,
+
+    /// The horizontal bounds of the plot
+    #[arg(
+        long,
+        value_name = "MIN,MAX",
+        value_parser = range,
+        allow_hyphen_values = true,
+        global = true
+    )]
+    pub x_range: Option<(f64, f64)>,
+
+    /// The vertical bounds of the plot
+    #[arg(
+        long,
+        value_name = "MIN,MAX",
+        value_parser = range,
+        allow_hyphen_values = true,
+        global = true
+    )]
+    pub y_range: Option<(f64, f64)>,
+}
+
+/// Parses a finite, positive number.
+fn positive(s: &str) -> Result<f64, String> {
+    let value = s
+        .parse::<f64>()
+        .map_err(|e| format!("{} is not a number: {}", s, e))?;
+
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("{} is not a finite, positive number", s))
+    }
+}
+
+/// Parses bounds `MIN,MAX` with MIN < MAX.
+fn range(s: &str) -> Result<(f64, f64), String> {
+    let (min, max) = s
+        .split_once(',')
+        .ok_or_else(|| format!("Expected MIN,MAX, got {}", s))?;
+
+    let parse = |bound: &str| match bound.trim().parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(format!("{} is not a finite number", bound)),
+    };
+    let (min, max) = (parse(min)?, parse(max)?);
+
+    if min < max {
+        Ok((min, max))
+    } else {
+        Err(format!(
+            "The minimum {} must be less than the maximum {}",
+            min, max
+        ))
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Solve and write the plot or the solution to files, without opening a window
+    Render {
+        /// The files to write, as .png, .svg, .csv, .json or .npy by their extension
+        #[arg(short, long = "output", value_name = "FILE", required = true)]
+        outputs: Vec<PathBuf>,
+
+        /// The width of the images in pixels
+        #[arg(long, default_value_t = 1920)]
+        width: u32,
+
+        /// The height of the images in pixels
+        #[arg(long, default_value_t = 1080)]
+        height: u32,
+    },
+    /// Solve over the whole integration span and print the solution to stdout
+    Solve {
+        /// The format of the table
+        #[arg(short, long, value_enum, default_value_t = TableFormat::Csv)]
+        format: TableFormat,
+
+        /// Resample the solution at this many evenly spaced points instead of
+        /// printing the steps of the solver
+        #[arg(long, value_name = "N")]
+        samples: Option<usize>,
+    },
+}
+
+/// The formats `dydx solve` prints the solution in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TableFormat {
+    /// A header of the column names followed by one row per point
+    Csv,
+    /// The table with the solver, its statistics and why it stopped
+    Json,
 }
@@ -1,6 +1,11 @@
 #![allow(dead_code)]
 
-use nannou::color::Srgba;
+use nannou::{
+    color::{IntoLinSrgba, Srgba},
+    prelude::{map_range, pt2, srgba, Point2, Rect, GRAY, WHITE},
+};
+
+use crate::canvas::{Canvas, Justify, Style};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Axis {
@@ -84,6 +89,109 @@ impl Default for Axes {
     }
 }
 
+impl Axes {
+    pub fn x_limits(&self) -> (f64, f64) {
+        (self.min_x, self.max_x)
+    }
+
+    pub fn y_limits(&self) -> (f64, f64) {
+        (self.min_y, self.max_y)
+    }
+
+    /// Maps a point in axes coordinates to the screen, within `rect`.
+    pub fn to_screen(&self, rect: &Rect, x: f64, y: f64) -> Point2 {
+        let x = map_range(x, self.min_x, self.max_x, rect.left(), rect.right());
+        let y = map_range(y, self.min_y, self.max_y, rect.bottom(), rect.top());
+        pt2(x, y)
+    }
+
+    /// Maps a point on the screen within `rect` to axes coordinates.
+    pub fn from_screen(&self, rect: &Rect, p: Point2) -> (f64, f64) {
+        let x = map_range(p.x, rect.left(), rect.right(), self.min_x, self.max_x);
+        let y = map_range(p.y, rect.bottom(), rect.top(), self.min_y, self.max_y);
+        (x, y)
+    }
+
+    /// Draws the frame, axis lines and limits of the axes into `rect`.
+    pub fn draw(&self, draw: &impl Canvas, rect: &Rect, title: &str) {
+        let frame = Style::fill(srgba(0.05, 0.05, 0.05, 0.9)).with_stroke(GRAY, 1.0);
+        draw.rect(*rect, frame);
+
+        let x_axis = match self.x_axis_location {
+            XAxisLocation::Top => self.max_y,
+            XAxisLocation::Center => 0.0f64.clamp(self.min_y, self.max_y),
+            XAxisLocation::Bottom => self.min_y,
+        };
+
+        let y_axis = match self.y_axis_location {
+            YAxisLocation::Left => self.min_x,
+            YAxisLocation::Center => 0.0f64.clamp(self.min_x, self.max_x),
+            YAxisLocation::Right => self.max_x,
+        };
+
+        draw.line(
+            self.to_screen(rect, self.min_x, x_axis),
+            self.to_screen(rect, self.max_x, x_axis),
+            1.0,
+            GRAY,
+        );
+
+        draw.line(
+            self.to_screen(rect, y_axis, self.min_y),
+            self.to_screen(rect, y_axis, self.max_y),
+            1.0,
+            GRAY,
+        );
+
+        let label = |text: String, p: Point2| {
+            let rect = Rect::from_xy_wh(p, pt2(80.0, 14.0));
+            draw.text(&text, rect, 11, Justify::Center, WHITE);
+        };
+
+        let pad = 10.0;
+        label(title.to_string(), pt2(rect.x(), rect.top() - pad));
+        label(
+            format!("{:.3}", self.max_y),
+            pt2(rect.left() + 40.0, rect.top() - pad),
+        );
+        label(
+            format!("{:.3}", self.min_y),
+            pt2(rect.left() + 40.0, rect.bottom() + pad),
+        );
+        label(
+            format!("{:.3}", self.min_x),
+            pt2(rect.left() + 40.0, rect.bottom() - pad),
+        );
+        label(
+            format!("{:.3}", self.max_x),
+            pt2(rect.right() - 40.0, rect.bottom() - pad),
+        );
+    }
+
+    /// Draws the series `(xs, ys)` as a polyline, skipping points outside of the axes.
+    pub fn draw_series(
+        &self,
+        draw: &impl Canvas,
+        rect: &Rect,
+        xs: &[f64],
+        ys: &[f64],
+        color: impl IntoLinSrgba<f32>,
+    ) {
+        let points = xs
+            .iter()
+            .zip(ys)
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .map(|(&x, &y)| {
+                let y = y.clamp(self.min_y, self.max_y);
+                self.to_screen(rect, x, y)
+            })
+            .filter(|p| rect.contains(*p))
+            .collect::<Vec<_>>();
+
+        draw.polyline_colored(&points, 1.5, color);
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AxesBuilder {
     axes: Axes,
@@ -120,6 +228,34 @@ impl AxesBuilder {
         self
     }
 
+    /// Sets the limits to fit the data, with a small padding around it.
+    pub fn fit_data(mut self, xs: &[f64], ys: &[f64]) -> Self {
+        let range = |values: &[f64]| {
+            let finite = values.iter().copied().filter(|v| v.is_finite());
+            let min = finite.clone().fold(f64::INFINITY, f64::min);
+            let max = finite.fold(f64::NEG_INFINITY, f64::max);
+
+            if !min.is_finite() || !max.is_finite() {
+                return None;
+            }
+
+            let pad = if max > min { 0.05 * (max - min) } else { 1.0 };
+            Some((min - pad, max + pad))
+        };
+
+        if let Some((min_x, max_x)) = range(xs) {
+            self.axes.min_x = min_x;
+            self.axes.max_x = max_x;
+        }
+
+        if let Some((min_y, max_y)) = range(ys) {
+            self.axes.min_y = min_y;
+            self.axes.max_y = max_y;
+        }
+
+        self
+    }
+
     pub fn build(self) -> Axes {
         self.axes
     }
@@ -0,0 +1,366 @@
+use std::{cell::RefCell, fmt::Write, path::Path};
+
+use anyhow::{anyhow, Result};
+use nannou::{
+    color::{IntoLinSrgba, LinSrgba},
+    prelude::{Draw, Point2, Rect},
+};
+
+/// How text is aligned horizontally within its rect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Justify {
+    Left,
+    Center,
+    Right,
+}
+
+/// How a shape is filled and outlined.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Style {
+    pub fill: Option<LinSrgba>,
+    /// The color and weight of the outline
+    pub stroke: Option<(LinSrgba, f32)>,
+}
+
+impl Style {
+    pub fn fill(color: impl IntoLinSrgba<f32>) -> Self {
+        Self {
+            fill: Some(color.into_lin_srgba()),
+            stroke: None,
+        }
+    }
+
+    pub fn stroke(color: impl IntoLinSrgba<f32>, weight: f32) -> Self {
+        Self::default().with_stroke(color, weight)
+    }
+
+    pub fn with_stroke(mut self, color: impl IntoLinSrgba<f32>, weight: f32) -> Self {
+        self.stroke = Some((color.into_lin_srgba(), weight));
+        self
+    }
+}
+
+/// The primitives the plot is drawn with, so that it can be drawn both to the
+/// screen with nannou and to a vector image.
+///
+/// Coordinates are those of nannou, with the origin at the center and y pointing up.
+pub trait Canvas {
+    /// Draws a polyline with a color per vertex.
+    fn polyline(&self, points: &[(Point2, LinSrgba)], weight: f32);
+
+    fn line(&self, start: Point2, end: Point2, weight: f32, color: impl IntoLinSrgba<f32>);
+
+    fn ellipse(&self, center: Point2, radius: f32, style: Style);
+
+    fn rect(&self, rect: Rect, style: Style);
+
+    fn text(
+        &self,
+        text: &str,
+        rect: Rect,
+        font_size: u32,
+        justify: Justify,
+        color: impl IntoLinSrgba<f32>,
+    );
+
+    /// Draws a polyline in a single color.
+    fn polyline_colored(&self, points: &[Point2], weight: f32, color: impl IntoLinSrgba<f32>) {
+        let color = color.into_lin_srgba();
+        let points = points.iter().map(|&p| (p, color)).collect::<Vec<_>>();
+        self.polyline(&points, weight);
+    }
+}
+
+impl Canvas for Draw {
+    fn polyline(&self, points: &[(Point2, LinSrgba)], weight: f32) {
+        if points.len() < 2 {
+            return;
+        }
+
+        self.polyline()
+            .weight(weight)
+            .join_round()
+            .points_colored(points.iter().copied());
+    }
+
+    fn line(&self, start: Point2, end: Point2, weight: f32, color: impl IntoLinSrgba<f32>) {
+        self.line()
+            .start(start)
+            .end(end)
+            .weight(weight)
+            .color(color.into_lin_srgba());
+    }
+
+    fn ellipse(&self, center: Point2, radius: f32, style: Style) {
+        let ellipse = self.ellipse().xy(center).radius(radius);
+
+        match style {
+            Style {
+                fill: Some(fill),
+                stroke: Some((stroke, weight)),
+            } => {
+                ellipse.color(fill).stroke(stroke).stroke_weight(weight);
+            }
+            Style {
+                fill: Some(fill),
+                stroke: None,
+            } => {
+                ellipse.color(fill);
+            }
+            Style {
+                fill: None,
+                stroke: Some((stroke, weight)),
+            } => {
+                ellipse.no_fill().stroke(stroke).stroke_weight(weight);
+            }
+            Style {
+                fill: None,
+                stroke: None,
+            } => {
+                ellipse.no_fill();
+            }
+        }
+    }
+
+    fn rect(&self, rect: Rect, style: Style) {
+        let drawing = self.rect().xy(rect.xy()).wh(rect.wh());
+
+        match style {
+            Style {
+                fill: Some(fill),
+                stroke: Some((stroke, weight)),
+            } => {
+                drawing.color(fill).stroke(stroke).stroke_weight(weight);
+            }
+            Style {
+                fill: Some(fill),
+                stroke: None,
+            } => {
+                drawing.color(fill);
+            }
+            Style {
+                fill: None,
+                stroke: Some((stroke, weight)),
+            } => {
+                drawing.no_fill().stroke(stroke).stroke_weight(weight);
+            }
+            Style {
+                fill: None,
+                stroke: None,
+            } => {
+                drawing.no_fill();
+            }
+        }
+    }
+
+    fn text(
+        &self,
+        text: &str,
+        rect: Rect,
+        font_size: u32,
+        justify: Justify,
+        color: impl IntoLinSrgba<f32>,
+    ) {
+        let drawing = self
+            .text(text)
+            .xy(rect.xy())
+            .wh(rect.wh())
+            .font_size(font_size)
+            .color(color.into_lin_srgba());
+
+        match justify {
+            Justify::Left => drawing.left_justify(),
+            Justify::Center => drawing.center_justify(),
+            Justify::Right => drawing.right_justify(),
+        };
+    }
+}
+
+/// Records the primitives drawn within `bounds` as an SVG image.
+#[derive(Debug)]
+pub struct SvgCanvas {
+    bounds: Rect,
+    elements: RefCell<String>,
+}
+
+impl SvgCanvas {
+    pub fn new(bounds: Rect) -> Self {
+        Self {
+            bounds,
+            elements: RefCell::new(String::new()),
+        }
+    }
+
+    /// The SVG document of everything drawn so far.
+    pub fn finish(self) -> String {
+        let (w, h) = (self.bounds.w(), self.bounds.h());
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n{}</svg>\n",
+            self.elements.into_inner()
+        )
+    }
+
+    /// Maps a point from nannou's coordinates to the SVG's, with the origin at the top left and y pointing down.
+    fn point(&self, p: Point2) -> (f32, f32) {
+        (p.x - self.bounds.left(), self.bounds.top() - p.y)
+    }
+
+    fn push(&self, element: std::fmt::Arguments) {
+        let mut elements = self.elements.borrow_mut();
+        let _ = elements.write_fmt(element);
+        elements.push('\n');
+    }
+}
+
+/// Rasterizes an SVG document and saves it as a PNG, without a GPU.
+pub fn svg_to_png(svg: &str, path: &Path) -> Result<()> {
+    let mut options = resvg::usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+
+    let tree = resvg::usvg::Tree::from_str(svg, &options)
+        .map_err(|e| anyhow!("Failed to parse SVG: {}", e))?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| anyhow!("Invalid image size {}×{}", size.width(), size.height()))?;
+
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::default(),
+        &mut pixmap.as_mut(),
+    );
+
+    pixmap
+        .save_png(path)
+        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+}
+
+/// The sRGB hex code and opacity of a linear color.
+fn svg_color(color: LinSrgba) -> (String, f32) {
+    let encode = |c: f32| {
+        let c = c.clamp(0.0, 1.0);
+        let c = if c <= 0.0031308 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (c * 255.0).round() as u8
+    };
+
+    let hex = format!(
+        "#{:02x}{:02x}{:02x}",
+        encode(color.red),
+        encode(color.green),
+        encode(color.blue)
+    );
+
+    (hex, color.alpha)
+}
+
+fn svg_style(style: Style) -> String {
+    let fill = match style.fill {
+        Some(fill) => {
+            let (hex, alpha) = svg_color(fill);
+            format!("fill=\"{hex}\" fill-opacity=\"{alpha}\"")
+        }
+        None => "fill=\"none\"".to_string(),
+    };
+
+    let stroke = match style.stroke {
+        Some((stroke, weight)) => {
+            let (hex, alpha) = svg_color(stroke);
+            format!(" stroke=\"{hex}\" stroke-opacity=\"{alpha}\" stroke-width=\"{weight}\"")
+        }
+        None => String::new(),
+    };
+
+    fill + &stroke
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl Canvas for SvgCanvas {
+    fn polyline(&self, points: &[(Point2, LinSrgba)], weight: f32) {
+        let Some(&(_, first)) = points.first() else {
+            return;
+        };
+
+        if points.iter().all(|&(_, color)| color == first) {
+            let (hex, alpha) = svg_color(first);
+            let coordinates = points
+                .iter()
+                .map(|&(p, _)| {
+                    let (x, y) = self.point(p);
+                    format!("{x:.2},{y:.2}")
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            self.push(format_args!(
+                "<polyline points=\"{coordinates}\" fill=\"none\" stroke=\"{hex}\" stroke-opacity=\"{alpha}\" stroke-width=\"{weight}\" stroke-linejoin=\"round\" stroke-linecap=\"round\"/>"
+            ));
+            return;
+        }
+
+        // SVG has no per-vertex colors, so each segment takes the color of its start.
+        for pair in points.windows(2) {
+            let (start, color) = pair[0];
+            self.line(start, pair[1].0, weight, color);
+        }
+    }
+
+    fn line(&self, start: Point2, end: Point2, weight: f32, color: impl IntoLinSrgba<f32>) {
+        let (x1, y1) = self.point(start);
+        let (x2, y2) = self.point(end);
+        let (hex, alpha) = svg_color(color.into_lin_srgba());
+
+        self.push(format_args!(
+            "<line x1=\"{x1:.2}\" y1=\"{y1:.2}\" x2=\"{x2:.2}\" y2=\"{y2:.2}\" stroke=\"{hex}\" stroke-opacity=\"{alpha}\" stroke-width=\"{weight}\" stroke-linecap=\"round\"/>"
+        ));
+    }
+
+    fn ellipse(&self, center: Point2, radius: f32, style: Style) {
+        let (cx, cy) = self.point(center);
+        let style = svg_style(style);
+
+        self.push(format_args!(
+            "<circle cx=\"{cx:.2}\" cy=\"{cy:.2}\" r=\"{radius}\" {style}/>"
+        ));
+    }
+
+    fn rect(&self, rect: Rect, style: Style) {
+        let (x, y) = self.point(rect.top_left());
+        let (w, h) = (rect.w(), rect.h());
+        let style = svg_style(style);
+
+        self.push(format_args!(
+            "<rect x=\"{x:.2}\" y=\"{y:.2}\" width=\"{w:.2}\" height=\"{h:.2}\" {style}/>"
+        ));
+    }
+
+    fn text(
+        &self,
+        text: &str,
+        rect: Rect,
+        font_size: u32,
+        justify: Justify,
+        color: impl IntoLinSrgba<f32>,
+    ) {
+        let (anchor, x) = match justify {
+            Justify::Left => ("start", rect.left()),
+            Justify::Center => ("middle", rect.x()),
+            Justify::Right => ("end", rect.right()),
+        };
+        let (x, y) = self.point(nannou::prelude::pt2(x, rect.y()));
+        let (hex, alpha) = svg_color(color.into_lin_srgba());
+        let text = escape(text);
+
+        self.push(format_args!(
+            "<text x=\"{x:.2}\" y=\"{y:.2}\" font-family=\"sans-serif\" font-size=\"{font_size}\" fill=\"{hex}\" fill-opacity=\"{alpha}\" text-anchor=\"{anchor}\" dominant-baseline=\"middle\">{text}</text>"
+        ));
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use symbolica::{
+    atom::Atom,
+    evaluate::{ExpressionEvaluator, FunctionMap, OptimizationSettings},
+};
+
+use dydx_core::OdeSettings;
+
+use crate::colormap::Colormap;
+
+/// The quantity a trajectory is colored by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorBy {
+    /// A single color
+    Solid,
+    /// The independent variable
+    Time,
+    /// The norm of the right-hand side |f|
+    Speed,
+    /// A user expression over the independent and state variables
+    Expression,
+}
+
+impl ColorBy {
+    pub const ALL: [ColorBy; 4] = [
+        ColorBy::Solid,
+        ColorBy::Time,
+        ColorBy::Speed,
+        ColorBy::Expression,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorBy::Solid => "Solid",
+            ColorBy::Time => "Time",
+            ColorBy::Speed => "Speed |f|",
+            ColorBy::Expression => "Expression",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrajectoryColoring {
+    pub by: ColorBy,
+    pub colormap: Colormap,
+    /// The expression colored by with `ColorBy::Expression`
+    pub expression: String,
+}
+
+impl Default for TrajectoryColoring {
+    fn default() -> Self {
+        Self {
+            by: ColorBy::Solid,
+            colormap: Colormap::Viridis,
+            expression: "y".to_string(),
+        }
+    }
+}
+
+/// What the evaluator of a coloring is compiled from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorKey {
+    /// The parsed right-hand side and the variables, for the speed
+    Speed(Result<Vec<Atom>, String>, Vec<Atom>),
+    /// The color expression as entered and the variables
+    Expression(String, Vec<Atom>),
+}
+
+/// The colored quantity compiled for evaluation along trajectories.
+#[derive(Debug, Clone)]
+pub struct ColorEvaluator {
+    evaluator: ExpressionEvaluator<f64>,
+    outputs: usize,
+}
+
+impl ColorEvaluator {
+    pub fn new(key: &ColorKey) -> Result<Self> {
+        let (expressions, variables) = match key {
+            ColorKey::Speed(expressions, variables) => (
+                expressions
+                    .clone()
+                    .map_err(|e| anyhow::anyhow!("Failed to parse expressions: {}", e))?,
+                variables,
+            ),
+            ColorKey::Expression(expression, variables) => (
+                vec![Atom::parse(expression)
+                    .map_err(|e| anyhow::anyhow!("Failed to parse color expression: {}", e))?],
+                variables,
+            ),
+        };
+
+        let views = expressions
+            .iter()
+            .map(|expr| expr.as_view())
+            .collect::<Vec<_>>();
+
+        let evaluator = Atom::evaluator_multiple(
+            views.as_slice(),
+            &FunctionMap::new(),
+            variables.as_slice(),
+            OptimizationSettings::default(),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to create evaluator: {:?}", e))?
+        .map_coeff(&|x| x.into());
+
+        Ok(Self {
+            evaluator,
+            outputs: expressions.len(),
+        })
+    }
+}
+
+impl TrajectoryColoring {
+    /// What the evaluator of the coloring is compiled from, or `None` if the
+    /// colored quantity needs no evaluator.
+    pub fn key(&self, settings: &OdeSettings) -> Option<ColorKey> {
+        match self.by {
+            ColorBy::Solid | ColorBy::Time => None,
+            ColorBy::Speed => Some(ColorKey::Speed(
+                settings.inputs.parsed_expressions.clone(),
+                settings.variables(),
+            )),
+            ColorBy::Expression => Some(ColorKey::Expression(
+                self.expression.clone(),
+                settings.variables(),
+            )),
+        }
+    }
+
+    /// The colored quantity at each sampled point `(t, y)`, or `None` for a
+    /// solid color, evaluated with the evaluator compiled for its `key`.
+    pub fn values(
+        &self,
+        evaluator: Option<&Result<ColorEvaluator, String>>,
+        domain: &[f64],
+        image: &[Vec<f64>],
+    ) -> Result<Option<Vec<f64>>> {
+        match self.by {
+            ColorBy::Solid => return Ok(None),
+            ColorBy::Time => return Ok(Some(domain.to_vec())),
+            ColorBy::Speed | ColorBy::Expression => {}
+        }
+
+        let mut evaluator = match evaluator {
+            Some(Ok(evaluator)) => evaluator.clone(),
+            Some(Err(e)) => bail!("{}", e),
+            None => bail!("The coloring has no evaluator"),
+        };
+
+        let mut out = vec![0.0; evaluator.outputs];
+        let values = domain
+            .iter()
+            .zip(image)
+            .map(|(&t, y)| {
+                let in_ = std::iter::once(&t).chain(y).copied().collect::<Vec<_>>();
+                evaluator.evaluator.evaluate(&in_, &mut out);
+
+                match self.by {
+                    ColorBy::Speed => out.iter().map(|f| f * f).sum::<f64>().sqrt(),
+                    _ => out[0],
+                }
+            })
+            .collect();
+
+        Ok(Some(values))
+    }
+}
+
+/// The range of the finite values, or `None` if there are none.
+pub fn value_range(values: &[f64]) -> Option<(f64, f64)> {
+    let finite = values.iter().copied().filter(|v| v.is_finite());
+    let min = finite.clone().fold(f64::INFINITY, f64::min);
+    let max = finite.fold(f64::NEG_INFINITY, f64::max);
+
+    (min <= max).then_some((min, max))
+}
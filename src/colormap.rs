@@ -0,0 +1,108 @@
+use nannou::color::{rgb, Rgb};
+use serde::{Deserialize, Serialize};
+
+/// Perceptually uniform colormaps from matplotlib.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Colormap {
+    Viridis,
+    Magma,
+    Inferno,
+    Plasma,
+    Cividis,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 5] = [
+        Colormap::Viridis,
+        Colormap::Magma,
+        Colormap::Inferno,
+        Colormap::Plasma,
+        Colormap::Cividis,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Colormap::Viridis => "viridis",
+            Colormap::Magma => "magma",
+            Colormap::Inferno => "inferno",
+            Colormap::Plasma => "plasma",
+            Colormap::Cividis => "cividis",
+        }
+    }
+
+    /// Evenly spaced colors of the colormap, from 0 to 1.
+    fn stops(&self) -> &'static [(u8, u8, u8)] {
+        match self {
+            Colormap::Viridis => &[
+                (68, 1, 84),
+                (72, 40, 120),
+                (62, 73, 137),
+                (49, 104, 142),
+                (38, 130, 142),
+                (31, 158, 137),
+                (53, 183, 121),
+                (110, 206, 88),
+                (253, 231, 37),
+            ],
+            Colormap::Magma => &[
+                (0, 0, 4),
+                (28, 16, 68),
+                (79, 18, 123),
+                (129, 37, 129),
+                (181, 54, 122),
+                (229, 80, 100),
+                (251, 135, 97),
+                (254, 194, 135),
+                (252, 253, 191),
+            ],
+            Colormap::Inferno => &[
+                (0, 0, 4),
+                (31, 12, 72),
+                (85, 15, 109),
+                (136, 34, 106),
+                (186, 54, 85),
+                (227, 89, 51),
+                (249, 140, 10),
+                (249, 201, 50),
+                (252, 255, 164),
+            ],
+            Colormap::Plasma => &[
+                (13, 8, 135),
+                (76, 2, 161),
+                (126, 3, 168),
+                (169, 35, 149),
+                (204, 71, 120),
+                (230, 108, 92),
+                (248, 149, 64),
+                (253, 197, 39),
+                (240, 249, 33),
+            ],
+            Colormap::Cividis => &[
+                (0, 32, 77),
+                (65, 77, 107),
+                (124, 123, 120),
+                (188, 175, 111),
+                (255, 234, 70),
+            ],
+        }
+    }
+
+    /// The color at `t` in [0, 1], interpolating linearly between the stops.
+    pub fn sample(&self, t: f32) -> Rgb {
+        let stops = self.stops();
+        let t = if t.is_finite() {
+            t.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let position = t * (stops.len() - 1) as f32;
+        let i = (position.floor() as usize).min(stops.len() - 2);
+        let s = position - i as f32;
+
+        let channel = |a: u8, b: u8| (a as f32 + s * (b as f32 - a as f32)) / 255.0;
+        let (a, b) = (stops[i], stops[i + 1]);
+
+        rgb(channel(a.0, b.0), channel(a.1, b.1), channel(a.2, b.2))
+    }
+}
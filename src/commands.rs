@@ -0,0 +1,515 @@
+use std::{collections::BTreeMap, fmt, path::Path, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use nannou::winit::event::{ModifiersState, VirtualKeyCode};
+use nannou_egui::egui;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config_dir;
+use crate::presets::Preset;
+
+/// Everything that can be bound to a key or run from the command palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    CommandPalette,
+    Undo,
+    Redo,
+    /// Restore the bounds of the plot to those it started with
+    ResetView,
+    /// Show or hide the particles animating the vector field
+    ToggleFlow,
+    /// Remove the solutions of the compared solvers and the particle trails
+    ClearTrajectories,
+    /// Switch to the next solver
+    CycleSolver,
+    ExportPng,
+    ExportSvg,
+    SaveSession,
+    OpenSession,
+    /// Open the command palette on the presets
+    OpenPreset,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::CommandPalette,
+        Action::Undo,
+        Action::Redo,
+        Action::ResetView,
+        Action::ToggleFlow,
+        Action::ClearTrajectories,
+        Action::CycleSolver,
+        Action::ExportPng,
+        Action::ExportSvg,
+        Action::SaveSession,
+        Action::OpenSession,
+        Action::OpenPreset,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::CommandPalette => "Command palette",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::ResetView => "Reset view",
+            Action::ToggleFlow => "Toggle flow animation",
+            Action::ClearTrajectories => "Clear trajectories",
+            Action::CycleSolver => "Cycle solver",
+            Action::ExportPng => "Export PNG",
+            Action::ExportSvg => "Export SVG",
+            Action::SaveSession => "Save session",
+            Action::OpenSession => "Open session",
+            Action::OpenPreset => "Open preset",
+        }
+    }
+}
+
+/// The bindings used unless the config file changes them.
+const DEFAULT_BINDINGS: [(&str, Action); 13] = [
+    ("Ctrl+P", Action::CommandPalette),
+    ("Ctrl+Z", Action::Undo),
+    ("Ctrl+Shift+Z", Action::Redo),
+    ("Ctrl+Y", Action::Redo),
+    ("R", Action::ResetView),
+    ("F", Action::ToggleFlow),
+    ("C", Action::ClearTrajectories),
+    ("S", Action::CycleSolver),
+    ("Ctrl+E", Action::ExportPng),
+    ("Ctrl+Shift+E", Action::ExportSvg),
+    ("Ctrl+S", Action::SaveSession),
+    ("Ctrl+O", Action::OpenSession),
+    ("Ctrl+Shift+O", Action::OpenPreset),
+];
+
+/// The names of the keys that can be bound, as written in the config file.
+const KEYS: [(&str, VirtualKeyCode); 67] = [
+    ("A", VirtualKeyCode::A),
+    ("B", VirtualKeyCode::B),
+    ("C", VirtualKeyCode::C),
+    ("D", VirtualKeyCode::D),
+    ("E", VirtualKeyCode::E),
+    ("F", VirtualKeyCode::F),
+    ("G", VirtualKeyCode::G),
+    ("H", VirtualKeyCode::H),
+    ("I", VirtualKeyCode::I),
+    ("J", VirtualKeyCode::J),
+    ("K", VirtualKeyCode::K),
+    ("L", VirtualKeyCode::L),
+    ("M", VirtualKeyCode::M),
+    ("N", VirtualKeyCode::N),
+    ("O", VirtualKeyCode::O),
+    ("P", VirtualKeyCode::P),
+    ("Q", VirtualKeyCode::Q),
+    ("R", VirtualKeyCode::R),
+    ("S", VirtualKeyCode::S),
+    ("T", VirtualKeyCode::T),
+    ("U", VirtualKeyCode::U),
+    ("V", VirtualKeyCode::V),
+    ("W", VirtualKeyCode::W),
+    ("X", VirtualKeyCode::X),
+    ("Y", VirtualKeyCode::Y),
+    ("Z", VirtualKeyCode::Z),
+    ("0", VirtualKeyCode::Key0),
+    ("1", VirtualKeyCode::Key1),
+    ("2", VirtualKeyCode::Key2),
+    ("3", VirtualKeyCode::Key3),
+    ("4", VirtualKeyCode::Key4),
+    ("5", VirtualKeyCode::Key5),
+    ("6", VirtualKeyCode::Key6),
+    ("7", VirtualKeyCode::Key7),
+    ("8", VirtualKeyCode::Key8),
+    ("9", VirtualKeyCode::Key9),
+    ("F1", VirtualKeyCode::F1),
+    ("F2", VirtualKeyCode::F2),
+    ("F3", VirtualKeyCode::F3),
+    ("F4", VirtualKeyCode::F4),
+    ("F5", VirtualKeyCode::F5),
+    ("F6", VirtualKeyCode::F6),
+    ("F7", VirtualKeyCode::F7),
+    ("F8", VirtualKeyCode::F8),
+    ("F9", VirtualKeyCode::F9),
+    ("F10", VirtualKeyCode::F10),
+    ("F11", VirtualKeyCode::F11),
+    ("F12", VirtualKeyCode::F12),
+    ("Escape", VirtualKeyCode::Escape),
+    ("Space", VirtualKeyCode::Space),
+    ("Tab", VirtualKeyCode::Tab),
+    ("Enter", VirtualKeyCode::Return),
+    ("Backspace", VirtualKeyCode::Back),
+    ("Delete", VirtualKeyCode::Delete),
+    ("Home", VirtualKeyCode::Home),
+    ("End", VirtualKeyCode::End),
+    ("PageUp", VirtualKeyCode::PageUp),
+    ("PageDown", VirtualKeyCode::PageDown),
+    ("Left", VirtualKeyCode::Left),
+    ("Right", VirtualKeyCode::Right),
+    ("Up", VirtualKeyCode::Up),
+    ("Down", VirtualKeyCode::Down),
+    ("Comma", VirtualKeyCode::Comma),
+    ("Period", VirtualKeyCode::Period),
+    ("Slash", VirtualKeyCode::Slash),
+    ("Minus", VirtualKeyCode::Minus),
+    ("Equals", VirtualKeyCode::Equals),
+];
+
+/// A key with the modifiers held with it, written like `Ctrl+Shift+Z`.
+///
+/// Ctrl also matches the Command key, so that the same bindings work on macOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    ctrl: bool,
+    shift: bool,
+    alt: bool,
+    key: VirtualKeyCode,
+}
+
+impl KeyChord {
+    pub fn matches(&self, key: VirtualKeyCode, mods: ModifiersState) -> bool {
+        self.key == key
+            && self.ctrl == (mods.ctrl() || mods.logo())
+            && self.shift == mods.shift()
+            && self.alt == mods.alt()
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+
+        let key = KEYS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|&(_, key)| key)
+            .ok_or_else(|| anyhow!("Unknown key {} in {}", key, s))?;
+
+        let mut chord = KeyChord {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            key,
+        };
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" => chord.ctrl = true,
+                "shift" => chord.shift = true,
+                "alt" | "option" => chord.alt = true,
+                _ => return Err(anyhow!("Unknown modifier {} in {}", modifier, s)),
+            }
+        }
+
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+        ] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+
+        let name = KEYS
+            .iter()
+            .find(|&&(_, key)| key == self.key)
+            .map_or("?", |&(name, _)| name);
+        f.write_str(name)
+    }
+}
+
+/// The actions bound to keys.
+pub struct Keybindings {
+    bindings: Vec<(KeyChord, Action)>,
+}
+
+impl Keybindings {
+    /// The default bindings, changed by `keybindings.json` in the config
+    /// directory if there is one.
+    ///
+    /// The file maps chords to actions, e.g. `{ "Ctrl+R": "reset-view", "R": null }`,
+    /// where `null` removes a default binding.
+    pub fn load() -> Self {
+        let mut bindings = Self::defaults();
+
+        let Some(path) = config_dir().map(|dir| dir.join("keybindings.json")) else {
+            return bindings;
+        };
+        if !path.exists() {
+            return bindings;
+        }
+
+        match read_bindings(&path) {
+            Ok(changes) => bindings.change(changes),
+            Err(e) => warn!("Using the default keybindings: {}", e),
+        }
+
+        bindings
+    }
+
+    fn defaults() -> Self {
+        Self {
+            bindings: DEFAULT_BINDINGS
+                .iter()
+                .map(|&(chord, action)| {
+                    (chord.parse().expect("Default bindings are valid"), action)
+                })
+                .collect(),
+        }
+    }
+
+    /// Binds each chord to its action, or unbinds it for `None`.
+    fn change(&mut self, changes: Vec<(KeyChord, Option<Action>)>) {
+        for (chord, action) in changes {
+            self.bindings.retain(|(c, _)| *c != chord);
+            if let Some(action) = action {
+                self.bindings.push((chord, action));
+            }
+        }
+    }
+
+    /// The action bound to `key` with the modifiers `mods`, if any.
+    pub fn action(&self, key: VirtualKeyCode, mods: ModifiersState) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(chord, _)| chord.matches(key, mods))
+            .map(|&(_, action)| action)
+    }
+
+    /// The first chord bound to `action`, for display.
+    pub fn chord(&self, action: Action) -> Option<KeyChord> {
+        self.bindings
+            .iter()
+            .find(|&&(_, a)| a == action)
+            .map(|&(chord, _)| chord)
+    }
+}
+
+fn read_bindings(path: &Path) -> Result<Vec<(KeyChord, Option<Action>)>> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let bindings: BTreeMap<String, Option<Action>> = serde_json::from_str(&json)
+        .map_err(|e| anyhow!("Invalid keybindings {}: {}", path.display(), e))?;
+
+    bindings
+        .into_iter()
+        .map(|(chord, action)| Ok((chord.parse()?, action)))
+        .collect()
+}
+
+/// What the command palette can run: the actions and loading each preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteCommand {
+    Action(Action),
+    Preset(usize),
+}
+
+/// A fuzzy search over every command.
+#[derive(Debug, Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    query: String,
+    /// The index of the highlighted match
+    selected: usize,
+}
+
+impl CommandPalette {
+    /// Opens the palette with `query` already typed.
+    pub fn show(&mut self, query: &str) {
+        self.open = true;
+        self.query = query.to_string();
+        self.selected = 0;
+    }
+}
+
+/// Shows the palette while it is open, returning the command picked with a
+/// click or Enter.
+pub fn palette_ui(
+    ctx: &egui::Context,
+    palette: &mut CommandPalette,
+    keybindings: &Keybindings,
+    presets: &[Preset],
+) -> Option<PaletteCommand> {
+    if !palette.open {
+        return None;
+    }
+
+    let commands = Action::ALL
+        .into_iter()
+        .filter(|&action| action != Action::CommandPalette)
+        .map(|action| {
+            let chord = keybindings.chord(action).map(|chord| chord.to_string());
+            (
+                action.name().to_string(),
+                chord,
+                PaletteCommand::Action(action),
+            )
+        })
+        .chain(presets.iter().enumerate().map(|(i, preset)| {
+            let label = format!("Preset: {}", preset.title());
+            (label, None, PaletteCommand::Preset(i))
+        }));
+
+    let mut picked = None;
+
+    egui::Window::new("Command palette")
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+        .show(ctx, |ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut palette.query)
+                    .hint_text("Type a command")
+                    .desired_width(320.0),
+            );
+            response.request_focus();
+            if response.changed() {
+                palette.selected = 0;
+            }
+
+            let mut matches: Vec<_> = commands
+                .filter_map(|(label, chord, command)| {
+                    fuzzy_score(&palette.query, &label).map(|score| (score, label, chord, command))
+                })
+                .collect();
+            matches.sort_by(|a, b| b.0.cmp(&a.0));
+
+            let (up, down, enter, escape) = ui.input(|i| {
+                (
+                    i.key_pressed(egui::Key::ArrowUp),
+                    i.key_pressed(egui::Key::ArrowDown),
+                    i.key_pressed(egui::Key::Enter),
+                    i.key_pressed(egui::Key::Escape),
+                )
+            });
+            if up {
+                palette.selected = palette.selected.saturating_sub(1);
+            }
+            if down {
+                palette.selected += 1;
+            }
+            palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    for (i, (_, label, chord, command)) in matches.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.selectable_label(i == palette.selected, label).clicked() {
+                                picked = Some(*command);
+                            }
+                            if let Some(chord) = chord {
+                                ui.weak(chord);
+                            }
+                        });
+                    }
+                });
+
+            if enter {
+                picked = matches.get(palette.selected).map(|m| m.3);
+            }
+            if escape || picked.is_some() {
+                palette.open = false;
+            }
+        });
+
+    picked
+}
+
+/// Scores how well `query` matches `text` as a subsequence, ignoring case and
+/// spaces, favouring runs of characters and the starts of words. `None` if the
+/// query isn't a subsequence of the text.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous = None;
+
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = next + text[next..].iter().position(|&c| c == q)?;
+
+        score += match previous {
+            Some(p) if p + 1 == i => 5,
+            _ => 1,
+        };
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+
+        previous = Some(i);
+        next = i + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(s: &str) -> KeyChord {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn default_bindings_parse_and_round_trip() {
+        for (name, _) in DEFAULT_BINDINGS {
+            let parsed = chord(name);
+            assert_eq!(parsed.to_string(), name);
+            assert_eq!(chord(&parsed.to_string()), parsed);
+        }
+
+        assert_eq!(chord("ctrl + shift + z"), chord("Ctrl+Shift+Z"));
+        assert_eq!(chord("Cmd+P"), chord("Ctrl+P"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert!("Ctrl+Foo".parse::<KeyChord>().is_err());
+        assert!("".parse::<KeyChord>().is_err());
+        assert!("Hyper+A".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn prefix_matches_rank_above_scattered_ones() {
+        let prefix = fuzzy_score("save", "Save session").unwrap();
+        let scattered = fuzzy_score("save", "Sample value").unwrap();
+        assert!(prefix > scattered, "{} <= {}", prefix, scattered);
+
+        assert_eq!(fuzzy_score("xyz", "Undo"), None);
+        assert_eq!(fuzzy_score("ods", "Undo"), None);
+    }
+
+    #[test]
+    fn config_file_rebinds_and_unbinds_keys() {
+        let path = std::env::temp_dir().join("dydx-keybindings-test.json");
+        std::fs::write(&path, r#"{ "Ctrl+R": "reset-view", "R": null }"#).unwrap();
+        let changes = read_bindings(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut bindings = Keybindings::defaults();
+        bindings.change(changes.unwrap());
+
+        let ctrl = ModifiersState::CTRL;
+        let none = ModifiersState::empty();
+        assert_eq!(
+            bindings.action(VirtualKeyCode::R, ctrl),
+            Some(Action::ResetView)
+        );
+        assert_eq!(bindings.action(VirtualKeyCode::R, none), None);
+        assert_eq!(bindings.chord(Action::ResetView), Some(chord("Ctrl+R")));
+
+        // The other defaults are kept.
+        assert_eq!(bindings.action(VirtualKeyCode::Z, ctrl), Some(Action::Undo));
+    }
+}
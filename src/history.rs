@@ -0,0 +1,354 @@
+use nannou_egui::egui::{self, RichText};
+use serde_json::Value;
+
+use crate::Settings;
+
+/// The number of changes kept for undo, beyond which the oldest are dropped.
+const MAX_ENTRIES: usize = 100;
+
+/// A state of the settings, with the change that separates it from its
+/// neighbour on the other stack.
+struct Entry {
+    settings: Settings,
+    label: String,
+}
+
+/// Undo and redo over snapshots of the settings.
+///
+/// Changes are found by comparing the serialized settings each frame, so every
+/// edit is covered without the panels reporting them. While a gesture is in
+/// progress, such as a drag or typing into a field, changes accumulate and are
+/// recorded as one entry once it ends.
+pub struct History {
+    /// The states before each change, oldest first
+    undo: Vec<Entry>,
+    /// The states after each undone change, the next to redo last
+    redo: Vec<Entry>,
+    /// The last recorded state
+    current: Settings,
+    current_value: Value,
+    /// A jump through the history requested from the panel
+    pub requested: Option<HistoryAction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryAction {
+    /// Undo this many changes
+    Undo(usize),
+    /// Redo this many changes
+    Redo(usize),
+}
+
+impl History {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            undo: vec![],
+            redo: vec![],
+            current: settings.clone(),
+            current_value: fingerprint(settings),
+            requested: None,
+        }
+    }
+
+    /// Records the settings if they changed and no gesture is in progress,
+    /// labelling the entry by the fields that changed.
+    pub fn track(&mut self, settings: &Settings, gesture: bool) {
+        if gesture {
+            return;
+        }
+
+        let value = fingerprint(settings);
+        if value != self.current_value {
+            let label = describe_change(&self.current_value, &value);
+            self.push(settings, value, label);
+        }
+    }
+
+    /// Records the settings under `label` if they changed, for changes made
+    /// at once such as opening a session.
+    pub fn record(&mut self, settings: &Settings, label: &str) {
+        let value = fingerprint(settings);
+        if value != self.current_value {
+            self.push(settings, value, label.to_string());
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Reverts the last `count` changes, recording any pending change first so
+    /// that it can be redone.
+    pub fn undo(&mut self, settings: &mut Settings, count: usize) {
+        self.track(settings, false);
+
+        for _ in 0..count {
+            let Some(entry) = self.undo.pop() else { break };
+            let after = std::mem::replace(&mut self.current, entry.settings);
+            self.redo.push(Entry {
+                settings: after,
+                label: entry.label,
+            });
+        }
+
+        self.restore(settings);
+    }
+
+    /// Reapplies the last `count` undone changes. A pending change starts a new
+    /// branch of the history, so there is then nothing to redo.
+    pub fn redo(&mut self, settings: &mut Settings, count: usize) {
+        self.track(settings, false);
+
+        for _ in 0..count {
+            let Some(entry) = self.redo.pop() else { break };
+            let before = std::mem::replace(&mut self.current, entry.settings);
+            self.undo.push(Entry {
+                settings: before,
+                label: entry.label,
+            });
+        }
+
+        self.restore(settings);
+    }
+
+    pub fn apply(&mut self, settings: &mut Settings, action: HistoryAction) {
+        match action {
+            HistoryAction::Undo(count) => self.undo(settings, count),
+            HistoryAction::Redo(count) => self.redo(settings, count),
+        }
+    }
+
+    fn push(&mut self, settings: &Settings, value: Value, label: String) {
+        let before = std::mem::replace(&mut self.current, settings.clone());
+        self.current_value = value;
+        self.undo.push(Entry {
+            settings: before,
+            label,
+        });
+        self.redo.clear();
+
+        if self.undo.len() > MAX_ENTRIES {
+            self.undo.remove(0);
+        }
+    }
+
+    fn restore(&mut self, settings: &mut Settings) {
+        *settings = self.current.clone();
+        self.current_value = fingerprint(settings);
+    }
+}
+
+/// Lists the states from the oldest: the initial state, then the state
+/// after each change, clicking one requesting the jump to it.
+pub fn history_ui(ui: &mut egui::Ui, history: &mut History) {
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(history.can_undo(), egui::Button::new("Undo"))
+            .on_hover_text("Ctrl+Z")
+            .clicked()
+        {
+            history.requested = Some(HistoryAction::Undo(1));
+        }
+
+        if ui
+            .add_enabled(history.can_redo(), egui::Button::new("Redo"))
+            .on_hover_text("Ctrl+Shift+Z")
+            .clicked()
+        {
+            history.requested = Some(HistoryAction::Redo(1));
+        }
+    });
+
+    let undone = history.undo.len();
+    egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .show(ui, |ui| {
+            if ui.selectable_label(undone == 0, "Start").clicked() && undone > 0 {
+                history.requested = Some(HistoryAction::Undo(undone));
+            }
+
+            for (i, entry) in history.undo.iter().enumerate() {
+                let current = i + 1 == undone;
+                if ui.selectable_label(current, &entry.label).clicked() && !current {
+                    history.requested = Some(HistoryAction::Undo(undone - i - 1));
+                }
+            }
+
+            for (i, entry) in history.redo.iter().rev().enumerate() {
+                let label = RichText::new(&entry.label).weak();
+                if ui.selectable_label(false, label).clicked() {
+                    history.requested = Some(HistoryAction::Redo(i + 1));
+                }
+            }
+        });
+}
+
+/// The settings as they would be saved, leaving out what is derived from them.
+pub fn fingerprint(settings: &Settings) -> Value {
+    serde_json::json!({
+        "ode_settings": settings.ode_settings,
+        "plot_settings": settings.plot_settings,
+    })
+}
+
+/// Names the settings that differ between two fingerprints, e.g. "Initial
+/// conditions, bounds".
+fn describe_change(before: &Value, after: &Value) -> String {
+    let mut names: Vec<&str> = vec![];
+
+    for group in ["ode_settings", "plot_settings"] {
+        let (Some(before), Some(after)) = (before[group].as_object(), after[group].as_object())
+        else {
+            continue;
+        };
+
+        for (key, value) in after {
+            let name = field_name(key);
+            if before.get(key) != Some(value) && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    match names.len() {
+        0 => "Settings".to_string(),
+        1..=3 => {
+            let label = names.join(", ").replace('_', " ").to_lowercase();
+            let mut chars = label.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        n => format!("{} settings", n),
+    }
+}
+
+fn field_name(key: &str) -> &str {
+    match key {
+        "ics" => "Initial conditions",
+        "inputs" => "Equations",
+        "ode_solver" => "Solver",
+        "parameters" => "Solver parameters",
+        "integration_length" => "Integration length",
+        "coordinate" => "Coordinates",
+        "phase_axes" => "Phase axes",
+        "x_min" | "x_max" | "y_min" | "y_max" => "Bounds",
+        "time_cursor" => "Time cursor",
+        "time_series" => "Time series",
+        _ => key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dydx_core::OdeSettings;
+
+    use super::*;
+    use crate::PlotSettings;
+
+    fn settings(length: f64) -> Settings {
+        let mut settings = Settings {
+            ode_settings: OdeSettings::default(),
+            plot_settings: PlotSettings::default(),
+        };
+        settings.ode_settings.integration_length = length;
+        settings
+    }
+
+    fn length(settings: &Settings) -> f64 {
+        settings.ode_settings.integration_length
+    }
+
+    /// A history of the lengths 1, 2 and 3, with `settings` at the last.
+    fn three_changes() -> (History, Settings) {
+        let mut history = History::new(&settings(1.0));
+        history.track(&settings(2.0), false);
+        history.track(&settings(3.0), false);
+        (history, settings(3.0))
+    }
+
+    #[test]
+    fn undo_and_redo_restore_each_state() {
+        let (mut history, mut current) = three_changes();
+
+        history.undo(&mut current, 1);
+        assert_eq!(length(&current), 2.0);
+        history.undo(&mut current, 1);
+        assert_eq!(length(&current), 1.0);
+        assert!(!history.can_undo());
+
+        history.redo(&mut current, 1);
+        assert_eq!(length(&current), 2.0);
+        history.redo(&mut current, 1);
+        assert_eq!(length(&current), 3.0);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn change_after_undo_clears_redo() {
+        let (mut history, mut current) = three_changes();
+
+        history.undo(&mut current, 2);
+        assert!(history.can_redo());
+
+        history.track(&settings(4.0), false);
+        assert!(!history.can_redo());
+
+        let mut current = settings(4.0);
+        history.undo(&mut current, 1);
+        assert_eq!(length(&current), 1.0);
+    }
+
+    #[test]
+    fn history_is_trimmed() {
+        let mut history = History::new(&settings(0.0));
+        for i in 1..=MAX_ENTRIES + 10 {
+            history.track(&settings(i as f64), false);
+        }
+
+        assert_eq!(history.undo.len(), MAX_ENTRIES);
+
+        // The oldest states were dropped.
+        let mut current = settings((MAX_ENTRIES + 10) as f64);
+        history.undo(&mut current, MAX_ENTRIES);
+        assert_eq!(length(&current), 10.0);
+    }
+
+    #[test]
+    fn unchanged_settings_add_no_entries() {
+        let mut history = History::new(&settings(1.0));
+        history.track(&settings(1.0), false);
+        history.record(&settings(1.0), "Open session");
+        assert!(!history.can_undo());
+
+        // Nor do changes during a gesture, until it ends.
+        history.track(&settings(2.0), true);
+        assert!(!history.can_undo());
+        history.track(&settings(2.0), false);
+        assert_eq!(history.undo.len(), 1);
+    }
+
+    #[test]
+    fn changes_are_named_by_field() {
+        let before = settings(1.0);
+
+        let after = settings(2.0);
+        assert_eq!(
+            describe_change(&fingerprint(&before), &fingerprint(&after)),
+            "Integration length"
+        );
+
+        let mut after = settings(1.0);
+        after.ode_settings.ics = vec![5.0, 5.0];
+        after.plot_settings.x_max = 20.0;
+        assert_eq!(
+            describe_change(&fingerprint(&before), &fingerprint(&after)),
+            "Initial conditions, bounds"
+        );
+    }
+}
@@ -15,15 +15,16 @@ pub struct LoggingGuard {
 }
 
 pub fn configure_logging(level: tracing::Level) -> Result<LoggingGuard> {
-    // A layer that logs events to stdout
+    // A layer that logs events to stderr, which leaves stdout to `dydx solve`
     let crate_name = env!("CARGO_PKG_NAME");
 
-    let (non_blocking_stdout, guard_stdout) = tracing_appender::non_blocking(std::io::stdout());
+    let (non_blocking_stdout, guard_stdout) = tracing_appender::non_blocking(std::io::stderr());
     let stdout_printer = Printer::default().writer(non_blocking_stdout);
 
     let stdout_log = tracing_forest::ForestLayer::new(stdout_printer, NoTag);
     let std_filter = filter::Targets::new()
         .with_target(crate_name, level)
+        .with_target("dydx_core", level)
         .with_target("metrics", Level::TRACE);
 
     // A layer that logs events to a file
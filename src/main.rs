@@ -1,30 +1,71 @@
-use crate::args::Cli;
+use crate::args::{Cli, Command, TableFormat};
+use crate::axes_2d::{AxesBuilder, AxisLocation, YAxisLocation};
+use crate::canvas::{svg_to_png, Canvas, Justify, Style, SvgCanvas};
+use crate::coloring::{value_range, ColorBy, ColorEvaluator, ColorKey, TrajectoryColoring};
+use crate::colormap::Colormap;
+use crate::commands::{palette_ui, Action, CommandPalette, Keybindings, PaletteCommand};
+use crate::history::{fingerprint, history_ui, History};
 use crate::logging::configure_logging;
-use crate::ode::{solve_ode, OdeCoordinate, OdeSettings};
+use crate::particles::{ParticleSettings, Particles};
+use crate::presets::Preset;
+use crate::session::Session;
 
 use anyhow::{anyhow, Result};
 use clap::Parser;
+use dydx_core::{
+    energy, solve_ode, write_data, ClosedForm, ConvergenceSeries, ConvergenceSettings,
+    ConvergenceStudy, DataFormat, DataTable, EventDirection, EventHit, EventSpec, ExplicitMethod,
+    ExportSettings, GeneralSolution, InputMode, OdeCoordinate, OdeInputs, OdeSettings, OdeSolver,
+    Projection, Reference, Solution, SolutionForm, Tolerance, VectorField, Viewport,
+};
 use lazy_static::lazy_static;
-use nannou::prelude::{map_range, pt2, srgb, App, Draw, Frame, Rect, Update, BLACK, RED};
+use nannou::prelude::{
+    hsl, map_range, pt2, srgb, srgba, App, Frame, Hsl, IntoLinSrgba, LinSrgba, Point2, Rect,
+    Update, BLACK, GREEN, ORANGE, RED, WHITE, YELLOW,
+};
+use nannou::winit::event::{ElementState, KeyboardInput, WindowEvent};
 use nannou_egui::{
     egui::{self, RichText, TextStyle},
     Egui,
 };
-use std::panic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    io::Write,
+    panic,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 use symbolica::{atom::Atom, printer::PrintOptions, LicenseManager};
 use tracing::{debug, debug_span, error, info, info_span, warn};
 use tracing_unwrap::{OptionExt, ResultExt};
 
 mod args;
 mod axes_2d;
+mod canvas;
+mod coloring;
+mod colormap;
+mod commands;
 mod fonts;
+mod history;
 mod logging;
-mod ode;
+mod particles;
+mod presets;
+mod session;
 
 lazy_static! {
     pub static ref CLI: Cli = Cli::parse();
 }
 
+/// The directory of the user's configuration, `$XDG_CONFIG_HOME/dydx` or
+/// `~/.config/dydx`.
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|config| config.join("dydx"))
+}
+
 fn main() -> Result<()> {
     let log_level = CLI
         .verbose
@@ -52,17 +93,131 @@ fn main() -> Result<()> {
     LicenseManager::set_license_key(&licence)
         .map_err(|e| anyhow!("Failed to set license key: {}", e))?;
 
+    let settings = initial_settings(&CLI)?;
+
+    match &CLI.command {
+        Some(Command::Render {
+            outputs,
+            width,
+            height,
+        }) => return render(settings, outputs, (*width, *height)),
+        Some(Command::Solve { format, samples }) => {
+            return print_solution(
+                &mut std::io::stdout().lock(),
+                &settings.ode_settings,
+                *format,
+                *samples,
+            )
+        }
+        None => {}
+    }
+
     nannou::app(model).update(update).run();
 
     Ok(())
 }
 
-#[derive(Debug)]
+/// The settings given on the command line, on top of the session file if one is given.
+fn initial_settings(cli: &Cli) -> Result<Settings> {
+    let mut settings = match (&cli.session, &cli.preset) {
+        (Some(path), _) => Session::load(path)?.into_settings(),
+        (None, Some(name)) => presets::find(name)?.into_settings(),
+        (None, None) => Settings {
+            ode_settings: OdeSettings::default(),
+            plot_settings: PlotSettings::default(),
+        },
+    };
+
+    let ode_settings = &mut settings.ode_settings;
+
+    if cli.polar {
+        ode_settings.coordinate = OdeCoordinate::Polar;
+    }
+
+    if !cli.equations.is_empty() {
+        let equations = cli.equations.iter().map(String::as_str).collect::<Vec<_>>();
+
+        ode_settings.inputs = if cli.variables.is_empty() && equations.len() == 1 {
+            OdeInputs {
+                mode: InputMode::Scalar,
+                inputs: vec![equations[0].to_string()],
+                ..OdeSettings::default().inputs
+            }
+        } else if cli.variables.len() == equations.len() {
+            let variables = cli.variables.iter().map(String::as_str).collect::<Vec<_>>();
+            OdeInputs::system(&variables, &equations)
+        } else {
+            return Err(anyhow!(
+                "A system needs one variable per equation, got {} variables for {} equations",
+                cli.variables.len(),
+                equations.len()
+            ));
+        };
+
+        ode_settings.update_inputs();
+        if let Err(e) = &ode_settings.inputs.parsed_expressions {
+            return Err(anyhow!("Failed to parse equations: {}", e));
+        }
+    }
+
+    if !cli.ics.is_empty() {
+        if cli.ics.len() != ode_settings.ics.len() {
+            return Err(anyhow!(
+                "Expected {} initial conditions, got {}",
+                ode_settings.ics.len(),
+                cli.ics.len()
+            ));
+        }
+        ode_settings.ics = cli.ics.clone();
+    }
+
+    if let Some(length) = cli.length {
+        ode_settings.integration_length = length;
+    }
+    if let Some(solver) = cli.solver {
+        ode_settings.ode_solver = solver;
+    }
+    if let Some(tolerance) = cli.tolerance {
+        ode_settings.parameters.tolerance = Tolerance(tolerance);
+    }
+
+    let plot_settings = &mut settings.plot_settings;
+    if let Some(range) = cli.x_range {
+        (plot_settings.x_min, plot_settings.x_max) = range;
+    }
+    if let Some(range) = cli.y_range {
+        (plot_settings.y_min, plot_settings.y_max) = range;
+    }
+
+    settings.ode_settings.validate()?;
+    settings.plot_settings.validate()?;
+
+    Ok(settings)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 struct PlotSettings {
     x_min: f64,
     x_max: f64,
     y_min: f64,
     y_max: f64,
+    /// Also solve with RK4 and plot its energy drift, for Hamiltonian systems
+    compare_energy: bool,
+    /// Overlay the closed-form solution of scalar ODEs that have one
+    closed_form: bool,
+    /// Further solvers whose solutions are drawn over the main one
+    compared_solvers: Vec<OdeSolver>,
+    /// Draw the accepted steps as dots on the solution
+    show_steps: bool,
+    /// Plot the step sizes and local error estimates against the independent variable
+    step_diagnostics: bool,
+    /// The state components of a system plotted against time beside the main plot
+    time_series: Vec<usize>,
+    /// The value of the independent variable marked on every trajectory
+    time_cursor: Option<f64>,
+    particles: ParticleSettings,
+    coloring: TrajectoryColoring,
 }
 
 impl Default for PlotSettings {
@@ -72,25 +227,258 @@ impl Default for PlotSettings {
             x_max: 10.0,
             y_min: -10.0,
             y_max: 10.0,
+            compare_energy: true,
+            closed_form: true,
+            compared_solvers: vec![],
+            show_steps: false,
+            step_diagnostics: false,
+            time_series: vec![],
+            time_cursor: None,
+            particles: ParticleSettings::default(),
+            coloring: TrajectoryColoring::default(),
+        }
+    }
+}
+
+impl PlotSettings {
+    fn viewport(&self) -> Viewport {
+        Viewport {
+            x_min: self.x_min,
+            x_max: self.x_max,
+            y_min: self.y_min,
+            y_max: self.y_max,
+        }
+    }
+
+    fn set_viewport(&mut self, viewport: &Viewport) {
+        self.x_min = viewport.x_min;
+        self.x_max = viewport.x_max;
+        self.y_min = viewport.y_min;
+        self.y_max = viewport.y_max;
+    }
+
+    /// Checks that the bounds describe a non-empty, finite plot.
+    fn validate(&self) -> Result<()> {
+        let finite = [self.x_min, self.x_max, self.y_min, self.y_max]
+            .iter()
+            .all(|bound| bound.is_finite());
+
+        if !finite || self.x_min >= self.x_max || self.y_min >= self.y_max {
+            return Err(anyhow!(
+                "Invalid plot bounds x in [{}, {}], y in [{}, {}]",
+                self.x_min,
+                self.x_max,
+                self.y_min,
+                self.y_max
+            ));
         }
+
+        Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Settings {
-    ode_settings: ode::OdeSettings,
+    ode_settings: OdeSettings,
     plot_settings: PlotSettings,
 }
 
-struct Model {
+/// The settings with everything computed from them, which is all that is drawn.
+struct Scene {
     settings: Settings,
+    solution: Result<Solution>,
+    /// The time the solution took, shown alongside the compared solvers
+    wall_time: Duration,
+    /// The relative energy drift of each solver, for Hamiltonian systems
+    energy_drift: Vec<(OdeSolver, Vec<f64>, Vec<f64>)>,
+    /// The general closed-form solution, cached by the input it was derived from
+    general_solution: Option<(String, Result<Option<GeneralSolution>, String>)>,
+    closed_form: Result<Option<ClosedFormComparison>>,
+    convergence: ConvergencePanel,
+    /// The solutions of the compared solvers
+    comparisons: Vec<SolverComparison>,
+    /// Particles advected by the vector field, when animating the flow
+    particles: Particles,
+    /// The vector field of the particles, cached by the expressions and variables
+    /// it was compiled from
+    field: Option<(FieldKey, Result<VectorField, String>)>,
+    /// The evaluator of the trajectory coloring, cached by what it was compiled from
+    coloring: Option<(ColorKey, Result<ColorEvaluator, String>)>,
+    /// The fingerprint of the settings the solutions were computed for
+    solved: Option<Value>,
+}
+
+type FieldKey = (Result<Vec<Atom>, String>, Vec<Atom>);
+
+impl Scene {
+    fn new(settings: Settings) -> Self {
+        let mut scene = Self {
+            settings,
+            solution: Err(anyhow!("Not solved yet")),
+            wall_time: Duration::ZERO,
+            energy_drift: vec![],
+            general_solution: None,
+            closed_form: Ok(None),
+            convergence: ConvergencePanel::default(),
+            comparisons: vec![],
+            particles: Particles::default(),
+            field: None,
+            coloring: None,
+            solved: None,
+        };
+
+        scene.solve();
+        scene
+    }
+
+    /// Solves the ODE and everything compared against it for the current settings.
+    fn solve(&mut self) {
+        // The reference and compared solvers each cost as much as the main one,
+        // so only solve again when the settings change, which also keeps the
+        // timings steady.
+        let key = fingerprint(&self.settings);
+        if self.solved.as_ref() == Some(&key) {
+            return;
+        }
+        self.solved = Some(key);
+
+        let start = Instant::now();
+        self.solution = compute_ode_soln(&self.settings);
+        self.wall_time = start.elapsed();
+        self.energy_drift = match &self.solution {
+            Ok(solution) if self.settings.ode_settings.inputs.mode == InputMode::Hamiltonian => {
+                compute_energy_drift(&self.settings, solution)
+            }
+            _ => vec![],
+        };
+        self.closed_form = compute_closed_form(self);
+        self.comparisons = compute_comparisons(&self.settings);
+
+        // Compiling the coloring is slow, so only redo it when its expressions change.
+        let coloring = &self.settings.plot_settings.coloring;
+        if let Some(key) = coloring.key(&self.settings.ode_settings) {
+            if !matches!(&self.coloring, Some((cached, _)) if *cached == key) {
+                let evaluator = ColorEvaluator::new(&key).map_err(|e| e.to_string());
+                self.coloring = Some((key, evaluator));
+            }
+        }
+    }
+}
+
+struct Model {
+    scene: Scene,
+    /// The point `(t, y)` of the solution nearest to the pointer, if it is close to one
+    hover: Option<(f64, Vec<f64>)>,
+    export: ExportPanel,
+    session: SessionPanel,
+    history: History,
+    keybindings: Keybindings,
+    palette: CommandPalette,
+    /// The bounds of the plot as started or last opened, restored by resetting the view
+    home_view: Viewport,
     egui: Egui,
 }
 
+/// The solution of one of the compared solvers, with the time it took.
+struct SolverComparison {
+    solver: OdeSolver,
+    solution: Result<Solution>,
+    wall_time: Duration,
+}
+
+/// A convergence study of the solvers on the current problem, run on demand.
+#[derive(Default)]
+struct ConvergencePanel {
+    settings: ConvergenceSettings,
+    run_requested: bool,
+    study: Option<Result<ConvergenceStudy>>,
+    /// The outcome of the last CSV export
+    export: Option<Result<PathBuf>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Png,
+    Svg,
+    /// The solutions as data rather than the plot
+    Data(DataFormat),
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Svg => "svg",
+            ExportFormat::Data(format) => format.extension(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionAction {
+    Save,
+    Open,
+    /// Load the preset at this index of `SessionPanel::presets`
+    Preset(usize),
+}
+
+/// Saving and opening the settings as a session file.
+struct SessionPanel {
+    path: String,
+    requested: Option<SessionAction>,
+    /// The outcome of the last save or open
+    status: Option<Result<String>>,
+    /// The built-in and user presets, read once at startup
+    presets: Vec<Preset>,
+}
+
+impl Default for SessionPanel {
+    fn default() -> Self {
+        Self {
+            path: "session.json".to_string(),
+            requested: None,
+            status: None,
+            presets: vec![],
+        }
+    }
+}
+
+/// Exports of the plot as an image, at a resolution independent of the window,
+/// and of the solutions as data.
+struct ExportPanel {
+    width: u32,
+    height: u32,
+    data: ExportSettings,
+    requested: Option<ExportFormat>,
+    /// The files written by the last export, or why it failed
+    status: Option<Result<Vec<PathBuf>>>,
+}
+
+impl Default for ExportPanel {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            data: ExportSettings::default(),
+            requested: None,
+            status: None,
+        }
+    }
+}
+
+/// A closed-form solution compared against the numerical one.
+struct ClosedFormComparison {
+    closed_form: ClosedForm,
+    /// The points (x, y) of the closed-form solution
+    curve: Vec<(f64, f64)>,
+    /// The pointwise error |y - y_exact| at each step of the numerical solution
+    error: (Vec<f64>, Vec<f64>),
+}
+
 impl std::fmt::Debug for Model {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Model")
-            .field("settings", &self.settings)
+            .field("settings", &self.scene.settings)
             .finish()
     }
 }
@@ -112,12 +500,26 @@ fn model(app: &App) -> Model {
     info!("Setting fonts");
     fonts::set_fonts(&mut egui);
 
+    // nannou's model function takes no arguments, so the settings are built
+    // again rather than passed on from `main`. They were checked there before
+    // the app started, so this only fails if a session file changed since.
+    let settings = initial_settings(&CLI).expect_or_log("Invalid arguments");
+    let history = History::new(&settings);
+    let home_view = settings.plot_settings.viewport();
+
     Model {
         egui,
-        settings: Settings {
-            ode_settings: OdeSettings::default(),
-            plot_settings: PlotSettings::default(),
+        scene: Scene::new(settings),
+        hover: None,
+        export: ExportPanel::default(),
+        session: SessionPanel {
+            presets: presets::all(),
+            ..Default::default()
         },
+        history,
+        keybindings: Keybindings::load(),
+        palette: CommandPalette::default(),
+        home_view,
     }
 }
 
@@ -130,24 +532,159 @@ fn update(app: &App, model: &mut Model, update: Update) {
 
     let egui_wants_pointer = model.egui.ctx().wants_pointer_input();
 
+    let (plot, _) = layout(app.window_rect(), &model.scene.settings);
+    let on_plot = plot.contains(app.mouse.position());
+
     // Update model only if the left mouse button is down and egui doesn't want the pointer input.
-    if !egui_wants_pointer && app.mouse.buttons.left().is_down() {
+    if !egui_wants_pointer && on_plot && app.mouse.buttons.left().is_down() {
         // TODO: Ensure 2D
         let (x, y) = screen_to_point(
-            &model.settings.plot_settings,
-            &app.window_rect(),
+            &model.scene.settings.plot_settings,
+            &plot,
             app.mouse.x.into(),
             app.mouse.y.into(),
         );
         debug!("Mouse left: ({}, {})", x, y);
-        model.settings.ode_settings.ics = vec![x, y];
+
+        let ode_settings = &mut model.scene.settings.ode_settings;
+        match ode_settings.projection() {
+            Projection::Phase(i, j) => {
+                ode_settings.ics[i] = x;
+                ode_settings.ics[j] = y;
+            }
+            Projection::Graph | Projection::Polar => ode_settings.ics = vec![x, y],
+        }
     }
 
+    // Drags and typing are recorded once they end, as a single change.
+    let gesture = app.mouse.buttons.left().is_down() || model.egui.ctx().wants_keyboard_input();
+    model.history.track(&model.scene.settings, gesture);
+
     // TODO: change x/y bound on scroll
+
+    {
+        let span = debug_span!(target: "metrics", "compute_ode_soln");
+        let _enter = span.enter();
+
+        debug!(target: "metrics", "Computing ODE solution");
+        model.scene.solve();
+    }
+
+    let scene = &mut model.scene;
+    let particle_settings = &scene.settings.plot_settings.particles;
+    if particle_settings.enabled {
+        // Compiling the field is slow, so only redo it when the ODE changes.
+        let ode_settings = &scene.settings.ode_settings;
+        let key = (
+            ode_settings.inputs.parsed_expressions.clone(),
+            ode_settings.variables(),
+        );
+        if !matches!(&scene.field, Some((cached, _)) if *cached == key) {
+            let field = VectorField::new(ode_settings).map_err(|e| e.to_string());
+            scene.field = Some((key, field));
+        }
+
+        match &mut scene.field {
+            Some((_, Ok(field))) => {
+                field.update(ode_settings);
+                scene.particles.update(
+                    particle_settings,
+                    field,
+                    &scene.settings.plot_settings.viewport(),
+                    update.since_last.as_secs_f32(),
+                );
+            }
+            Some((_, Err(e))) => debug!("Not advecting particles: {}", e),
+            None => {}
+        }
+    }
+
+    model.hover = match &model.scene.solution {
+        Ok(solution) if on_plot && !egui_wants_pointer => {
+            nearest_point(solution, &model.scene.settings, &plot, app.mouse.position())
+        }
+        _ => None,
+    };
+
+    if let Some(format) = model.export.requested.take() {
+        let path = PathBuf::from(format!("dydx.{}", format.extension()));
+        let size = (model.export.width, model.export.height);
+        let result = match format {
+            ExportFormat::Png => export_png(&model.scene, &path, size).map(|_| vec![path]),
+            ExportFormat::Svg => export_svg(&model.scene, &path, size).map(|_| vec![path]),
+            ExportFormat::Data(format) => solution_tables(&model.scene, &model.export.data)
+                .and_then(|tables| write_data(&path, format, &tables)),
+        };
+        model.export.status = Some(result);
+    }
+}
+
+/// The point of the drawn curve of `solution` nearest to `pointer` on the screen,
+/// if it is within a few pixels.
+///
+/// The curve is sampled as it is drawn, and the pointer projected onto the
+/// nearest segment, so that hovering snaps anywhere along it rather than only
+/// to the ends of the solver's steps.
+fn nearest_point(
+    solution: &Solution,
+    settings: &Settings,
+    win: &Rect,
+    pointer: Point2,
+) -> Option<(f64, Vec<f64>)> {
+    const SNAP_DISTANCE: f32 = 12.0;
+
+    let projection = settings.ode_settings.projection();
+    let (domain, image) = sample_solution(solution, settings, win);
+
+    let screen = domain
+        .iter()
+        .zip(&image)
+        .map(|(&t, y)| {
+            let (x, y) = projection.project(t, y);
+            let (x, y) = point_to_screen(&settings.plot_settings, win, x, y);
+            (t, pt2(x as f32, y as f32))
+        })
+        .collect::<Vec<_>>();
+
+    let nearest_on_segment = |(t0, p0): (f64, Point2), (t1, p1): (f64, Point2)| {
+        let segment = p1 - p0;
+        let length = segment.length_squared();
+        let s = if length > 0.0 {
+            ((pointer - p0).dot(segment) / length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let distance = (p0 + segment * s).distance(pointer);
+        (distance, t0 + (t1 - t0) * s as f64)
+    };
+
+    let (distance, t) = match screen.as_slice() {
+        [point] => nearest_on_segment(*point, *point),
+        points => points
+            .windows(2)
+            .map(|pair| nearest_on_segment(pair[0], pair[1]))
+            .min_by(|a, b| a.0.total_cmp(&b.0))?,
+    };
+
+    if distance > SNAP_DISTANCE {
+        return None;
+    }
+
+    Some((t, solution.eval(t)?))
 }
 
 fn update_egui(model: &mut Model, update: Update) {
-    let settings = &mut model.settings;
+    let settings = &mut model.scene.settings;
+    let solution = &model.scene.solution;
+    let closed_form = &model.scene.closed_form;
+    let convergence = &mut model.scene.convergence;
+    let export = &mut model.export;
+    let session = &mut model.session;
+    let history = &mut model.history;
+    let palette = &mut model.palette;
+    let comparisons = &model.scene.comparisons;
+    let wall_time = model.scene.wall_time;
     let egui = &mut model.egui;
 
     egui.set_elapsed_time(update.since_start);
@@ -155,6 +692,14 @@ fn update_egui(model: &mut Model, update: Update) {
 
     let ode_settings = &mut settings.ode_settings;
     egui::Window::new("Settings").show(&ctx, |ui| {
+        egui::menu::bar(ui, |ui| {
+            ui.menu_button("File", |ui| session_ui(ui, session));
+        });
+
+        if let Some(Err(e)) = &session.status {
+            ui.colored_label(egui::Color32::RED, e.to_string());
+        }
+
         ui.horizontal(|ui| {
             ui.radio_value(
                 &mut ode_settings.coordinate,
@@ -169,40 +714,51 @@ fn update_egui(model: &mut Model, update: Update) {
 
         ui.separator();
 
-        if ode_settings.dimensions == 1 {
-            let ode_input = &mut ode_settings.inputs;
+        let mode = ode_settings.inputs.mode;
+        ui.horizontal(|ui| {
+            let inputs = &mut ode_settings.inputs;
+            ui.radio_value(&mut inputs.mode, InputMode::Scalar, "Scalar")
+                .on_hover_text("A single equation y' = f(x, y)");
+            ui.radio_value(&mut inputs.mode, InputMode::System, "System")
+                .on_hover_text("A first order system of equations in t");
+            ui.radio_value(&mut inputs.mode, InputMode::Hamiltonian, "Hamiltonian")
+                .on_hover_text("Derive Hamilton's equations from H(q, p)");
+            ui.radio_value(&mut inputs.mode, InputMode::Lagrangian, "Lagrangian")
+                .on_hover_text("Derive the Euler-Lagrange equations from L(q, q̇, t)");
+        });
 
-            ui.label("Input ODE");
-            ui.horizontal(|ui| {
-                ui.label("f(x, y) =");
-                let response = ui.text_edit_singleline(&mut ode_input.inputs[0]);
+        if ode_settings.inputs.mode != mode {
+            set_input_mode(ode_settings, ode_settings.inputs.mode);
+        }
+
+        match ode_settings.inputs.mode {
+            InputMode::Scalar => {
+                let ode_input = &mut ode_settings.inputs;
 
-                if response.changed() {
-                    let expr = Atom::parse(&ode_input.inputs[0]);
-                    if let Ok(expr) = expr {
-                        ode_input.parsed_expressions = Ok(vec![expr]);
+                ui.label("Input ODE");
+                ui.horizontal(|ui| {
+                    ui.label("f(x, y) =");
+                    let response = ui.text_edit_singleline(&mut ode_input.inputs[0]);
+
+                    if response.changed() {
+                        ode_input.parse_expressions();
                     }
-                }
-            });
-        } else {
-            ui.label("ODE is a system -- Coming soon!");
-        }
-
-        let opts = PrintOptions {
-            precision: None,
-            terms_on_new_line: false,
-            color_top_level_sum: false,
-            color_builtin_symbols: false,
-            print_finite_field: true,
-            symmetric_representation_for_finite_field: false,
-            explicit_rational_polynomial: false,
-            number_thousands_separator: None,
-            multiplication_operator: '*',
-            double_star_for_exponentiation: false,
-            square_brackets_for_function: false,
-            num_exp_as_superscript: true,
-            latex: false,
-        };
+                });
+            }
+            InputMode::System => system_inputs_ui(ui, ode_settings),
+            InputMode::Hamiltonian => {
+                mechanics_inputs_ui(ui, ode_settings, "H(q, p) =", "momentum", "p")
+            }
+            InputMode::Lagrangian => {
+                mechanics_inputs_ui(ui, ode_settings, "L(q, q̇, t) =", "velocity", "qdot")
+            }
+        }
+
+        if let Err(e) = &ode_settings.inputs.parsed_expressions {
+            ui.colored_label(egui::Color32::RED, e);
+        }
+
+        let opts = print_options();
 
         ode_settings.inputs.inputs.iter().for_each(|input| {
             let value = Atom::parse(input)
@@ -213,6 +769,20 @@ fn update_egui(model: &mut Model, update: Update) {
             ui.label(RichText::new(value).text_style(TextStyle::Name("STIXTwoMath".into())));
         });
 
+        if ode_settings.inputs.mode == InputMode::Scalar
+            && ode_settings.coordinate == OdeCoordinate::Cartesian
+        {
+            ui.checkbox(
+                &mut settings.plot_settings.closed_form,
+                "Closed-form solution",
+            )
+            .on_hover_text("Solve separable, linear, Bernoulli and exact equations exactly");
+
+            if settings.plot_settings.closed_form {
+                closed_form_ui(ui, closed_form);
+            }
+        }
+
         ui.separator();
 
         ui.label("initial conditions");
@@ -230,149 +800,1607 @@ fn update_egui(model: &mut Model, update: Update) {
         ui.add(
             egui::DragValue::new(&mut ode_settings.integration_length)
                 .speed(0.1)
-                .clamp_range(0..=20),
+                .clamp_range(0.1..=1000.0),
         );
-    });
-}
 
-fn draw_plot(draw: &Draw, win: &Rect, model: &Model, domain: &[f64], image: &[f64]) -> Result<()> {
-    let settings = &model.settings;
-    let plot_settings = &settings.plot_settings;
-    let ode_settings = &settings.ode_settings;
+        ui.horizontal(|ui| {
+            let time_cursor = &mut settings.plot_settings.time_cursor;
+            let domain = solution
+                .as_ref()
+                .ok()
+                .and_then(|solution| solution.domain());
+
+            let mut enabled = time_cursor.is_some();
+            ui.checkbox(&mut enabled, "Time cursor");
+
+            match (enabled, domain) {
+                (true, Some((start, end))) => {
+                    let mut t = time_cursor.unwrap_or(start).clamp(start, end);
+                    ui.add(egui::Slider::new(&mut t, start..=end));
+                    *time_cursor = Some(t);
+                }
+                _ => *time_cursor = None,
+            }
+        });
 
-    let col = srgb(31.0 / 255.0, 101.0 / 255.0, 245.0 / 255.0);
+        ui.separator();
 
-    let vertices = domain.iter().zip(image).map(|(&x, &y)| {
-        let (mut x, mut y) = (x, y);
+        ui.collapsing("Solver", |ui| {
+            egui::ComboBox::from_label("Method")
+                .selected_text(ode_settings.ode_solver.name())
+                .show_ui(ui, |ui| {
+                    for solver in OdeSolver::ALL {
+                        ui.selectable_value(&mut ode_settings.ode_solver, solver, solver.name());
+                    }
+                });
 
-        if ode_settings.coordinate == OdeCoordinate::Polar {
-            let r = x;
-            let theta = y;
-            x = r * theta.cos();
-            y = r * theta.sin();
-        }
+            let parameters = &mut ode_settings.parameters;
 
-        let (x, y) = point_to_screen(plot_settings, win, x, y);
-        (pt2(x as f32, y as f32), col)
-    });
+            ui.horizontal(|ui| {
+                ui.label("Tolerance");
+                ui.add(
+                    egui::DragValue::new(&mut parameters.tolerance.0)
+                        .speed(1e-5)
+                        .clamp_range(1e-12..=1.0),
+                );
+            });
 
-    let vertices = vertices.take_while(|(p, _)| {
-        let is_finite = p.x.is_finite() && p.y.is_finite();
+            ui.horizontal(|ui| {
+                ui.label("Step size");
+                ui.add(
+                    egui::DragValue::new(&mut parameters.initial_step_size)
+                        .speed(1e-4)
+                        .clamp_range(1e-6..=1.0),
+                )
+                .on_hover_text("Initial step size, and the step size of fixed-step methods");
+            });
 
-        if !is_finite {
-            let x_pt = map_range(
-                p.x,
-                win.left(),
-                win.right(),
-                plot_settings.x_min,
-                plot_settings.x_max,
-            );
-            warn!("Found non-finite point: {:?} (at x = {:?})", p, x_pt);
-        }
+            ui.horizontal(|ui| {
+                ui.label("Max step size");
+                ui.add(
+                    egui::DragValue::new(&mut parameters.max_step_size.0)
+                        .speed(1e-3)
+                        .clamp_range(1e-6..=10.0),
+                );
+            });
 
-        is_finite
-    });
+            let plot_settings = &mut settings.plot_settings;
+            ui.checkbox(&mut plot_settings.show_steps, "Show steps")
+                .on_hover_text("Mark each accepted step, in red if attempts were rejected");
+            ui.checkbox(&mut plot_settings.step_diagnostics, "Step diagnostics")
+                .on_hover_text("Plot the step size and local error estimate of each step");
+        });
 
-    // Draw the polyline as a stroked path.
-    let weight = 2.0;
-    draw.polyline()
-        .weight(weight)
-        .join_round()
-        .points_colored(vertices);
+        if ode_settings.inputs.mode == InputMode::Hamiltonian {
+            ui.checkbox(
+                &mut settings.plot_settings.compare_energy,
+                "Compare energy drift with RK4",
+            );
+        }
 
-    Ok(())
-}
+        ui.collapsing("Events", |ui| {
+            let mut removed = None;
 
-fn compute_ode_soln(ode_settings: &OdeSettings) -> Result<(Vec<f64>, Vec<Vec<f64>>)> {
-    if ode_settings.dimensions == 1 {
-        // TODO: Make animated 2D wavey bois
-        let (mut x0, mut y0) = (ode_settings.ics[0], ode_settings.ics[1]);
-        let mut xn = x0 + ode_settings.integration_length;
+            for (i, event) in ode_settings.events.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label("g(x, y) =");
+                    if ui.text_edit_singleline(&mut event.input).changed() {
+                        event.parse_expression();
+                    }
 
-        if ode_settings.coordinate == OdeCoordinate::Polar {
-            let x = x0;
-            let y = y0;
-            x0 = (x.powi(2) + y.powi(2)).sqrt();
-            xn = (xn.powi(2) + y.powi(2)).sqrt();
-            y0 = f64::atan2(y, x);
-        }
+                    if ui.button("✖").on_hover_text("Remove event").clicked() {
+                        removed = Some(i);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source(("event_direction", i))
+                        .selected_text(format!("{:?}", event.direction))
+                        .show_ui(ui, |ui| {
+                            for direction in [
+                                EventDirection::Both,
+                                EventDirection::Rising,
+                                EventDirection::Falling,
+                            ] {
+                                ui.selectable_value(
+                                    &mut event.direction,
+                                    direction,
+                                    format!("{:?}", direction),
+                                );
+                            }
+                        });
+
+                    ui.checkbox(&mut event.terminal, "Terminal")
+                        .on_hover_text("Stop integrating at the first crossing");
+                });
+
+                if let Err(e) = &event.parsed_expression {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+            }
 
-        let span = debug_span!(target: "metrics", "solve_ode");
-        let _enter = span.enter();
-        solve_ode(
-            ode_settings,
-            (x0, xn),
-            1e-3,
-            //&ode_settings.ics,
-            &[y0],
-        )
-    } else {
-        anyhow::bail!("System ODEs not implemented yet");
-    }
-}
+            if let Some(i) = removed {
+                ode_settings.events.remove(i);
+            }
 
-fn point_to_screen(plot_settings: &PlotSettings, win: &Rect, x: f64, y: f64) -> (f64, f64) {
-    let x = map_range(
-        x,
-        plot_settings.x_min,
-        plot_settings.x_max,
-        win.left().into(),
-        win.right().into(),
-    );
-    let y = map_range(
-        y,
-        plot_settings.y_min,
-        plot_settings.y_max,
-        win.bottom().into(),
-        win.top().into(),
-    );
-    (x, y)
-}
+            if ui.button("Add event").clicked() {
+                ode_settings.events.push(EventSpec::new("y"));
+            }
+        });
 
-fn screen_to_point(plot_settings: &PlotSettings, win: &Rect, x: f64, y: f64) -> (f64, f64) {
-    let x = map_range(
-        x,
-        win.left().into(),
-        win.right().into(),
-        plot_settings.x_min,
-        plot_settings.x_max,
-    );
-    let y = map_range(
-        y,
-        win.bottom().into(),
-        win.top().into(),
-        plot_settings.y_min,
-        plot_settings.y_max,
-    );
-    (x, y)
-}
+        ui.collapsing("Termination", |ui| {
+            let termination = &mut ode_settings.termination;
 
-fn raw_window_event(_app: &App, model: &mut Model, event: &nannou::winit::event::WindowEvent) {
-    // Let egui handle things like keyboard and mouse input.
-    model.egui.handle_raw_event(event);
-}
+            ui.checkbox(&mut termination.stop_non_finite, "Stop at non-finite state");
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let win = app.window_rect();
-    let draw = app.draw();
-    let settings = &model.settings;
+            ui.horizontal(|ui| {
+                let mut bounded = termination.max_norm.is_some();
+                ui.checkbox(&mut bounded, "Stop when |y| exceeds");
+
+                let mut bound = termination.max_norm.unwrap_or(1e8);
+                ui.add_enabled(
+                    bounded,
+                    egui::DragValue::new(&mut bound)
+                        .speed(10.0)
+                        .clamp_range(0.0..=f64::MAX),
+                );
+                termination.max_norm = bounded.then_some(bound);
+            });
 
-    draw.background().color(BLACK);
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut termination.stop_outside_viewport,
+                    "Stop outside viewport, margin",
+                );
+                ui.add_enabled(
+                    termination.stop_outside_viewport,
+                    egui::DragValue::new(&mut termination.viewport_margin)
+                        .speed(0.05)
+                        .clamp_range(0.0..=10.0),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Minimum step size");
+                ui.add(
+                    egui::DragValue::new(&mut termination.min_step_size)
+                        .speed(1e-7)
+                        .clamp_range(0.0..=1.0),
+                )
+                .on_hover_text(
+                    "Stop when the step size falls below this, under the solver's minimum",
+                );
+            });
+        });
+
+        if ode_settings.is_system() {
+            ui.collapsing("Time series", |ui| {
+                let time_series = &mut settings.plot_settings.time_series;
+
+                ui.horizontal_wrapped(|ui| {
+                    for (i, name) in ode_settings.state_names().iter().enumerate() {
+                        let mut checked = time_series.contains(&i);
+
+                        if ui.checkbox(&mut checked, name).changed() {
+                            if checked {
+                                time_series.push(i);
+                                time_series.sort_unstable();
+                            } else {
+                                time_series.retain(|&j| j != i);
+                            }
+                        }
+                    }
+                });
+            });
+        }
+
+        ui.collapsing("Coloring", |ui| {
+            let coloring = &mut settings.plot_settings.coloring;
+
+            egui::ComboBox::from_label("Color by")
+                .selected_text(coloring.by.name())
+                .show_ui(ui, |ui| {
+                    for by in ColorBy::ALL {
+                        ui.selectable_value(&mut coloring.by, by, by.name());
+                    }
+                });
+
+            egui::ComboBox::from_label("Colormap")
+                .selected_text(coloring.colormap.name())
+                .show_ui(ui, |ui| {
+                    for colormap in Colormap::ALL {
+                        ui.selectable_value(&mut coloring.colormap, colormap, colormap.name());
+                    }
+                });
+
+            if coloring.by == ColorBy::Expression {
+                ui.horizontal(|ui| {
+                    ui.label("c =");
+                    ui.text_edit_singleline(&mut coloring.expression);
+                });
+
+                if let Err(e) = Atom::parse(&coloring.expression) {
+                    ui.colored_label(egui::Color32::RED, e.to_string());
+                }
+            }
+        });
+
+        ui.collapsing("Particles", |ui| {
+            let particles = &mut settings.plot_settings.particles;
+
+            ui.checkbox(&mut particles.enabled, "Animate flow")
+                .on_hover_text("Advect particles along the vector field");
+
+            ui.horizontal(|ui| {
+                ui.label("Count");
+                ui.add(egui::DragValue::new(&mut particles.count).clamp_range(1..=5000));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Speed");
+                ui.add(
+                    egui::DragValue::new(&mut particles.speed)
+                        .speed(0.05)
+                        .clamp_range(0.01..=20.0),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Trail length");
+                ui.add(egui::DragValue::new(&mut particles.trail_length).clamp_range(1..=200));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Lifetime");
+                ui.add(
+                    egui::DragValue::new(&mut particles.lifetime)
+                        .speed(0.1)
+                        .clamp_range(0.1..=30.0),
+                )
+                .on_hover_text("The average lifetime of a particle in seconds");
+            });
+        });
+
+        ui.collapsing("Compare solvers", |ui| {
+            let primary = (ode_settings.ode_solver, solution, wall_time);
+            comparison_ui(ui, &mut settings.plot_settings, primary, comparisons)
+        });
+
+        ui.collapsing("Convergence study", |ui| convergence_ui(ui, convergence));
+
+        ui.collapsing("Export", |ui| export_ui(ui, export));
+
+        ui.collapsing("History", |ui| history_ui(ui, history));
+
+        match solution {
+            Ok(solution) => {
+                let stats = &solution.stats;
+                ui.label(format!(
+                    "Steps: {} accepted, {} rejected, {} evaluations",
+                    stats.accepted_steps, stats.rejected_steps, stats.function_evaluations
+                ));
+                for switch in &solution.switches {
+                    ui.label(format!(
+                        "Switched to {} at x = {:.4}",
+                        switch.to.name(),
+                        switch.t
+                    ));
+                }
+
+                ui.label(format!("Termination: {}", solution.termination))
+            }
+            Err(e) => ui.colored_label(egui::Color32::RED, format!("Failed to solve ODE: {}", e)),
+        };
+    });
+
+    let command = palette_ui(&ctx, palette, &model.keybindings, &session.presets);
+
+    // End the frame here, as commands can change any part of the model.
+    drop(ctx);
+
+    match command {
+        Some(PaletteCommand::Action(action)) => run_action(model, action),
+        Some(PaletteCommand::Preset(i)) => model.session.requested = Some(SessionAction::Preset(i)),
+        None => {}
+    }
+
+    // The study needs the whole scene, so it is run once the panel has released it.
+    if std::mem::take(&mut model.scene.convergence.run_requested) {
+        model.scene.convergence.study = Some(run_convergence_study(&model.scene));
+    }
+
+    if let Some(action) = model.session.requested.take() {
+        let path = PathBuf::from(&model.session.path);
+        model.session.status = Some(match action {
+            SessionAction::Save => Session::new(&model.scene.settings)
+                .save(&path)
+                .map(|_| format!("Saved to {}", path.display())),
+            SessionAction::Open => Session::load(&path).map(|session| {
+                model.scene.settings = session.into_settings();
+                let label = format!("Opened {}", path.display());
+                model.history.record(&model.scene.settings, &label);
+                model.home_view = model.scene.settings.plot_settings.viewport();
+                label
+            }),
+            SessionAction::Preset(i) => {
+                let preset = &model.session.presets[i];
+                model.scene.settings = preset.session.clone().into_settings();
+                let label = format!("Loaded {}", preset.title());
+                model.history.record(&model.scene.settings, &label);
+                model.home_view = model.scene.settings.plot_settings.viewport();
+                Ok(label)
+            }
+        });
+    }
+
+    if let Some(action) = model.history.requested.take() {
+        model.history.apply(&mut model.scene.settings, action);
+    }
+}
+
+fn session_ui(ui: &mut egui::Ui, session: &mut SessionPanel) {
+    ui.horizontal(|ui| {
+        ui.label("File");
+        ui.text_edit_singleline(&mut session.path);
+    });
+
+    ui.horizontal(|ui| {
+        if ui.button("Save").clicked() {
+            session.requested = Some(SessionAction::Save);
+            ui.close_menu();
+        }
+
+        if ui.button("Open").clicked() {
+            session.requested = Some(SessionAction::Open);
+            ui.close_menu();
+        }
+    });
+
+    ui.menu_button("Presets", |ui| {
+        for (i, preset) in session.presets.iter().enumerate() {
+            let button = ui.button(preset.title());
+            let button = match &preset.session.description {
+                Some(description) => button.on_hover_text(description.as_str()),
+                None => button,
+            };
+
+            if button.clicked() {
+                session.requested = Some(SessionAction::Preset(i));
+                ui.close_menu();
+            }
+        }
+    });
+
+    if let Some(Ok(status)) = &session.status {
+        ui.label(status);
+    }
+}
+
+fn print_options() -> PrintOptions {
+    PrintOptions {
+        precision: None,
+        terms_on_new_line: false,
+        color_top_level_sum: false,
+        color_builtin_symbols: false,
+        print_finite_field: true,
+        symmetric_representation_for_finite_field: false,
+        explicit_rational_polynomial: false,
+        number_thousands_separator: None,
+        multiplication_operator: '*',
+        double_star_for_exponentiation: false,
+        square_brackets_for_function: false,
+        num_exp_as_superscript: true,
+        latex: false,
+    }
+}
+
+fn closed_form_ui(ui: &mut egui::Ui, closed_form: &Result<Option<ClosedFormComparison>>) {
+    let comparison = match closed_form {
+        Ok(Some(comparison)) => comparison,
+        Ok(None) => {
+            ui.label("No closed-form solution found");
+            return;
+        }
+        Err(e) => {
+            ui.colored_label(egui::Color32::RED, e.to_string());
+            return;
+        }
+    };
+
+    let closed_form = &comparison.closed_form;
+    let opts = print_options();
+
+    let equation = match &closed_form.general.form {
+        SolutionForm::Explicit(e) => format!("y = {}", e.printer(opts)),
+        SolutionForm::Implicit(f) => format!("{} = C", f.printer(opts)),
+    };
+
+    ui.label(format!("{} equation", closed_form.general.class));
+    ui.label(RichText::new(equation).text_style(TextStyle::Name("STIXTwoMath".into())));
+    ui.label(
+        RichText::new(format!("C = {:.6}", closed_form.constant))
+            .text_style(TextStyle::Name("STIXTwoMath".into())),
+    );
+
+    if let Some(max) = comparison.error.1.iter().copied().reduce(f64::max) {
+        ui.label(format!("Max error: {:.3e}", max));
+    }
+}
+
+/// Selects the solvers to compare, and tabulates their statistics below those
+/// of the primary solver.
+fn comparison_ui(
+    ui: &mut egui::Ui,
+    plot_settings: &mut PlotSettings,
+    (solver, solution, wall_time): (OdeSolver, &Result<Solution>, Duration),
+    comparisons: &[SolverComparison],
+) {
+    let candidates = OdeSolver::ALL
+        .into_iter()
+        .filter(|solver| matches!(solver, OdeSolver::Explicit(_) | OdeSolver::Embedded(_)));
+
+    ui.horizontal_wrapped(|ui| {
+        for solver in candidates {
+            let compared = &mut plot_settings.compared_solvers;
+            let mut checked = compared.contains(&solver);
+
+            if ui.checkbox(&mut checked, solver.name()).changed() {
+                if checked {
+                    compared.push(solver);
+                } else {
+                    compared.retain(|&s| s != solver);
+                }
+            }
+        }
+    });
+
+    if comparisons.is_empty() {
+        return;
+    }
+
+    egui::Grid::new("solver_comparison")
+        .striped(true)
+        .show(ui, |ui| {
+            for heading in ["Method", "Steps", "Rejected", "Evaluations", "Time"] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+
+            comparison_row(ui, main_color(), solver, solution, wall_time);
+
+            for (i, comparison) in comparisons.iter().enumerate() {
+                comparison_row(
+                    ui,
+                    comparison_color(i).into_lin_srgba(),
+                    comparison.solver,
+                    &comparison.solution,
+                    comparison.wall_time,
+                );
+            }
+        });
+}
+
+fn comparison_row(
+    ui: &mut egui::Ui,
+    color: LinSrgba,
+    solver: OdeSolver,
+    solution: &Result<Solution>,
+    wall_time: Duration,
+) {
+    let color = egui::Rgba::from_rgb(color.red, color.green, color.blue);
+    ui.colored_label(color, solver.name());
+
+    match solution {
+        Ok(solution) => {
+            let stats = &solution.stats;
+            ui.label(stats.accepted_steps.to_string());
+            ui.label(stats.rejected_steps.to_string());
+            ui.label(stats.function_evaluations.to_string());
+        }
+        Err(e) => {
+            ui.colored_label(egui::Color32::RED, "failed")
+                .on_hover_text(e.to_string());
+            ui.label("");
+            ui.label("");
+        }
+    }
+
+    ui.label(format!("{:.2} ms", wall_time.as_secs_f64() * 1e3));
+    ui.end_row();
+}
+
+fn convergence_ui(ui: &mut egui::Ui, convergence: &mut ConvergencePanel) {
+    let settings = &mut convergence.settings;
+
+    ui.horizontal(|ui| {
+        ui.label("Coarsest steps");
+        ui.add(egui::DragValue::new(&mut settings.coarsest_steps).clamp_range(1..=1000));
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Refinements");
+        ui.add(egui::DragValue::new(&mut settings.refinements).clamp_range(1..=12))
+            .on_hover_text("The number of times the step size is halved");
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Reference tolerance");
+        ui.add(
+            egui::DragValue::new(&mut settings.reference_tolerance)
+                .speed(1e-13)
+                .clamp_range(1e-14..=1e-6),
+        )
+        .on_hover_text("Used when there is no closed-form solution to compare against");
+    });
+
+    ui.horizontal(|ui| {
+        if ui.button("Run").clicked() {
+            convergence.run_requested = true;
+        }
+
+        let study = match &convergence.study {
+            Some(Ok(study)) => Some(study),
+            _ => None,
+        };
+
+        if ui
+            .add_enabled(study.is_some(), egui::Button::new("Export CSV"))
+            .clicked()
+        {
+            if let Some(study) = study {
+                let path = PathBuf::from("convergence.csv");
+                convergence.export = Some(
+                    std::fs::write(&path, study.to_csv())
+                        .map(|_| path)
+                        .map_err(|e| anyhow!("Failed to export convergence study: {}", e)),
+                );
+            }
+        }
+
+        if ui.button("Clear").clicked() {
+            convergence.study = None;
+            convergence.export = None;
+        }
+    });
+
+    match &convergence.study {
+        Some(Ok(study)) => {
+            let reference = if study.exact_reference {
+                "closed-form solution"
+            } else {
+                "tight-tolerance DP45"
+            };
+            ui.label(format!("Errors against the {}", reference));
+
+            for series in &study.series {
+                let order = series
+                    .observed_order
+                    .map_or("-".to_string(), |order| format!("{:.2}", order));
+                ui.label(format!(
+                    "{}: observed order {}",
+                    series.solver.name(),
+                    order
+                ));
+            }
+        }
+        Some(Err(e)) => {
+            ui.colored_label(egui::Color32::RED, e.to_string());
+        }
+        None => {}
+    }
+
+    match &convergence.export {
+        Some(Ok(path)) => {
+            ui.label(format!("Exported to {}", path.display()));
+        }
+        Some(Err(e)) => {
+            ui.colored_label(egui::Color32::RED, e.to_string());
+        }
+        None => {}
+    }
+}
+
+fn export_ui(ui: &mut egui::Ui, export: &mut ExportPanel) {
+    ui.horizontal(|ui| {
+        ui.label("Resolution");
+        ui.add(egui::DragValue::new(&mut export.width).clamp_range(16..=8192));
+        ui.label("×");
+        ui.add(egui::DragValue::new(&mut export.height).clamp_range(16..=8192));
+    });
+
+    ui.horizontal(|ui| {
+        if ui.button("Export PNG").clicked() {
+            export.requested = Some(ExportFormat::Png);
+        }
+
+        if ui.button("Export SVG").clicked() {
+            export.requested = Some(ExportFormat::Svg);
+        }
+    });
+
+    ui.separator();
+
+    ui.checkbox(&mut export.data.derivatives, "Include derivatives");
+    ui.checkbox(&mut export.data.energy, "Include energy")
+        .on_hover_text("The value of the Hamiltonian, for Hamiltonian systems");
+
+    ui.horizontal(|ui| {
+        for format in DataFormat::ALL {
+            if ui
+                .button(format!("Export {}", format.extension().to_uppercase()))
+                .clicked()
+            {
+                export.requested = Some(ExportFormat::Data(format));
+            }
+        }
+    })
+    .response
+    .on_hover_text("Exports the solution and those of the compared solvers");
+
+    match &export.status {
+        Some(Ok(paths)) => {
+            let paths = paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>();
+            ui.label(format!("Exported to {}", paths.join(", ")));
+        }
+        Some(Err(e)) => {
+            ui.colored_label(egui::Color32::RED, e.to_string());
+        }
+        None => {}
+    }
+}
+
+/// Switches the input mode, replacing the inputs with an example for the new mode.
+fn set_input_mode(ode_settings: &mut OdeSettings, mode: InputMode) {
+    match mode {
+        InputMode::Scalar => {
+            let defaults = OdeSettings::default();
+            ode_settings.inputs = defaults.inputs;
+            ode_settings.ics = defaults.ics;
+        }
+        InputMode::System => {
+            ode_settings.inputs = OdeInputs::system(&["x", "y"], &["y", "-x"]);
+            ode_settings.ics = vec![1.0, 0.0];
+        }
+        InputMode::Hamiltonian => {
+            ode_settings.inputs = OdeInputs::hamiltonian("p^2/2 - cos(q)", &["q"], &["p"]);
+            ode_settings.ics = vec![1.0, 0.0];
+        }
+        InputMode::Lagrangian => {
+            ode_settings.inputs = OdeInputs::lagrangian("qdot^2/2 + cos(q)", &["q"], &["qdot"]);
+            ode_settings.ics = vec![1.0, 0.0];
+        }
+    }
+
+    ode_settings.update_inputs();
+}
+
+fn system_inputs_ui(ui: &mut egui::Ui, ode_settings: &mut OdeSettings) {
+    let inputs = &mut ode_settings.inputs;
+    let mut changed = false;
+    let mut removed = None;
+
+    ui.label("Input system");
+    for (i, (variable, input)) in inputs
+        .variables
+        .iter_mut()
+        .zip(&mut inputs.inputs)
+        .enumerate()
+    {
+        ui.horizontal(|ui| {
+            ui.label("d");
+            changed |= ui
+                .add(egui::TextEdit::singleline(variable).desired_width(30.0))
+                .changed();
+            ui.label("/dt =");
+            changed |= ui.text_edit_singleline(input).changed();
+
+            if ui.button("✖").on_hover_text("Remove equation").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+
+    if let Some(i) = removed.filter(|_| inputs.variables.len() > 2) {
+        inputs.variables.remove(i);
+        inputs.inputs.remove(i);
+        changed = true;
+    }
+
+    if ui.button("Add equation").clicked() {
+        inputs
+            .variables
+            .push(format!("u{}", inputs.variables.len()));
+        inputs.inputs.push("0".to_string());
+        changed = true;
+    }
+
+    if changed {
+        ode_settings.update_inputs();
+    }
+}
+
+/// Inputs for a Hamiltonian or Lagrangian in generalized coordinates.
+fn mechanics_inputs_ui(
+    ui: &mut egui::Ui,
+    ode_settings: &mut OdeSettings,
+    label: &str,
+    conjugate: &str,
+    conjugate_prefix: &str,
+) {
+    let inputs = &mut ode_settings.inputs;
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label(label);
+        changed |= ui.text_edit_singleline(&mut inputs.inputs[0]).changed();
+    });
+
+    for (q, p) in inputs.variables.iter_mut().zip(&mut inputs.conjugates) {
+        ui.horizontal(|ui| {
+            ui.label("Coordinate");
+            changed |= ui
+                .add(egui::TextEdit::singleline(q).desired_width(30.0))
+                .changed();
+            ui.label(conjugate);
+            changed |= ui
+                .add(egui::TextEdit::singleline(p).desired_width(30.0))
+                .changed();
+        });
+    }
+
+    ui.horizontal(|ui| {
+        if ui.button("Add coordinate").clicked() {
+            let n = inputs.variables.len() + 1;
+            inputs.variables.push(format!("q{}", n));
+            inputs.conjugates.push(format!("{}{}", conjugate_prefix, n));
+            changed = true;
+        }
+
+        if inputs.variables.len() > 1 && ui.button("Remove coordinate").clicked() {
+            inputs.variables.pop();
+            inputs.conjugates.pop();
+            changed = true;
+        }
+    });
+
+    if changed {
+        ode_settings.update_inputs();
+    }
+}
+
+/// The color of the solution of the selected solver.
+fn main_color() -> LinSrgba {
+    srgb(31.0 / 255.0, 101.0 / 255.0, 245.0 / 255.0).into_lin_srgba()
+}
+
+/// Draws the colormap of the trajectory as a vertical bar labelled with its
+/// range, to the left of the step diagnostics when they are shown.
+fn draw_colorbar(
+    draw: &impl Canvas,
+    win: &Rect,
+    plot_settings: &PlotSettings,
+    (min, max): (f64, f64),
+) {
+    const SEGMENTS: usize = 64;
+
+    let coloring = &plot_settings.coloring;
+    let offset = if plot_settings.step_diagnostics {
+        step_diagnostics_width(win) + 60.0
+    } else {
+        20.0
+    };
+    let bar = Rect::from_w_h(14.0, win.h() * 0.4)
+        .mid_right_of(win.pad(20.0))
+        .shift_x(-offset);
+    let h = bar.h() / SEGMENTS as f32;
+
+    for k in 0..SEGMENTS {
+        let s = (k as f32 + 0.5) / SEGMENTS as f32;
+        let segment = Rect::from_x_y_w_h(
+            bar.x(),
+            bar.bottom() + h * (k as f32 + 0.5),
+            bar.w(),
+            h + 0.5,
+        );
+        draw.rect(segment, Style::fill(coloring.colormap.sample(s)));
+    }
+
+    draw.rect(bar, Style::stroke(WHITE, 1.0));
+
+    let labels = [
+        (bar.top() + 10.0, format!("{:.3}", max)),
+        (bar.bottom() - 10.0, format!("{:.3}", min)),
+        (bar.top() + 24.0, coloring.by.name().to_string()),
+    ];
+    for (y, label) in labels {
+        let rect = Rect::from_x_y_w_h(bar.x(), y, 100.0, 14.0);
+        draw.text(&label, rect, 11, Justify::Center, WHITE);
+    }
+}
+
+/// Draws a sampled solution, with one color per sample.
+fn draw_plot(
+    draw: &impl Canvas,
+    win: &Rect,
+    scene: &Scene,
+    domain: &[f64],
+    image: &[Vec<f64>],
+    colors: &[LinSrgba],
+) -> Result<()> {
+    let settings = &scene.settings;
+    let plot_settings = &settings.plot_settings;
+    let ode_settings = &settings.ode_settings;
+
+    let projection = ode_settings.projection();
+
+    let vertices = domain.iter().zip(image).zip(colors).map(|((&t, y), &col)| {
+        let (x, y) = projection.project(t, y);
+        let (x, y) = point_to_screen(plot_settings, win, x, y);
+        (pt2(x as f32, y as f32), col)
+    });
+
+    let vertices = vertices.take_while(|(p, _)| {
+        let is_finite = p.x.is_finite() && p.y.is_finite();
+
+        if !is_finite {
+            let x_pt = map_range(
+                p.x,
+                win.left(),
+                win.right(),
+                plot_settings.x_min,
+                plot_settings.x_max,
+            );
+            warn!("Found non-finite point: {:?} (at x = {:?})", p, x_pt);
+        }
+
+        is_finite
+    });
+
+    // Draw the polyline as a stroked path.
+    let weight = 2.0;
+    draw.polyline(&vertices.collect::<Vec<_>>(), weight);
+
+    Ok(())
+}
+
+/// Draws the solutions of the compared solvers with a legend of their colors,
+/// headed by the primary solver.
+fn draw_comparisons(draw: &impl Canvas, win: &Rect, scene: &Scene) {
+    if scene.comparisons.is_empty() {
+        return;
+    }
+
+    let legend_rect = |row: usize| {
+        Rect::from_x_y_w_h(
+            win.left() + 90.0,
+            win.top() - 20.0 - 14.0 * row as f32,
+            140.0,
+            14.0,
+        )
+    };
+
+    let primary = scene.settings.ode_settings.ode_solver.name();
+    draw.text(primary, legend_rect(0), 12, Justify::Left, main_color());
+
+    for (i, comparison) in scene.comparisons.iter().enumerate() {
+        let color = comparison_color(i);
+
+        if let Ok(solution) = &comparison.solution {
+            let (domain, image) = sample_solution(solution, &scene.settings, win);
+            let colors = vec![color.into_lin_srgba(); domain.len()];
+            draw_plot(draw, win, scene, &domain, &image, &colors)
+                .unwrap_or_else(|e| error!("Error drawing comparison: {}", e));
+        }
+
+        let rect = legend_rect(i + 1);
+        draw.text(comparison.solver.name(), rect, 12, Justify::Left, color);
+    }
+}
+
+/// Marks the end of each accepted step on the solution.
+fn draw_steps(draw: &impl Canvas, win: &Rect, settings: &Settings, solution: &Solution) {
+    let projection = settings.ode_settings.projection();
+
+    for step in &solution.steps {
+        let (x, y) = projection.project(step.t + step.dt, &step.y);
+        let (x, y) = point_to_screen(&settings.plot_settings, win, x, y);
+        let col = if step.rejected.is_empty() { WHITE } else { RED };
+
+        draw.ellipse(pt2(x as f32, y as f32), 2.0, Style::fill(col));
+    }
+}
+
+/// The width of the step diagnostics, on the right of the plot.
+fn step_diagnostics_width(win: &Rect) -> f32 {
+    win.w() * 0.35
+}
+
+/// Plots the step size and the local error estimate of each step against the
+/// independent variable, with the rejected attempts at each step in red.
+fn draw_step_diagnostics(draw: &impl Canvas, win: &Rect, solution: &Solution) {
+    if solution.steps.is_empty() {
+        return;
+    }
+
+    let size = (step_diagnostics_width(win), win.h() * 0.18);
+    let dt_rect = Rect::from_w_h(size.0, size.1)
+        .mid_right_of(win.pad(20.0))
+        .shift_y(size.1 * 0.55);
+    let error_rect = dt_rect.below(dt_rect).shift_y(-10.0);
+
+    let ts = solution.steps.iter().map(|step| step.t).collect::<Vec<_>>();
+    let dts = solution
+        .steps
+        .iter()
+        .map(|step| step.dt)
+        .collect::<Vec<_>>();
+
+    let rejected = solution
+        .steps
+        .iter()
+        .flat_map(|step| step.rejected.iter().map(move |attempt| (step.t, attempt)))
+        .collect::<Vec<_>>();
+    let (rejected_ts, rejected_dts): (Vec<f64>, Vec<f64>) =
+        rejected.iter().map(|(t, attempt)| (*t, attempt.dt)).unzip();
+
+    let dt_axes = AxesBuilder::new()
+        .fit_data(
+            &[&ts[..], &rejected_ts].concat(),
+            &[&dts[..], &rejected_dts].concat(),
+        )
+        .set_axis_location(AxisLocation::Y(YAxisLocation::Left))
+        .build();
+
+    dt_axes.draw(draw, &dt_rect, "Step size");
+    dt_axes.draw_series(draw, &dt_rect, &ts, &dts, ORANGE);
+
+    for (t, attempt) in &rejected {
+        let p = dt_axes.to_screen(&dt_rect, *t, attempt.dt);
+        draw.ellipse(p, 2.5, Style::fill(RED));
+    }
+
+    // Error estimates span many orders of magnitude, so plot them logarithmically.
+    let log_error = |error: Option<f64>| Some(error.filter(|&e| e > 0.0)?.log10());
+    let (error_ts, errors): (Vec<f64>, Vec<f64>) = solution
+        .steps
+        .iter()
+        .filter_map(|step| Some((step.t, log_error(step.error)?)))
+        .unzip();
+    let (rejected_error_ts, rejected_errors): (Vec<f64>, Vec<f64>) = rejected
+        .iter()
+        .filter_map(|(t, attempt)| Some((*t, log_error(attempt.error)?)))
+        .unzip();
+
+    if errors.is_empty() {
+        return;
+    }
+
+    let error_axes = AxesBuilder::new()
+        .fit_data(
+            &[&error_ts[..], &rejected_error_ts].concat(),
+            &[&errors[..], &rejected_errors].concat(),
+        )
+        .set_axis_location(AxisLocation::Y(YAxisLocation::Left))
+        .build();
+
+    error_axes.draw(draw, &error_rect, "log₁₀ local error estimate");
+    error_axes.draw_series(draw, &error_rect, &error_ts, &errors, ORANGE);
+
+    for (t, error) in rejected_error_ts.iter().zip(&rejected_errors) {
+        let p = error_axes.to_screen(&error_rect, *t, *error);
+        draw.ellipse(p, 2.5, Style::fill(RED));
+    }
+}
+
+fn draw_events(draw: &impl Canvas, win: &Rect, scene: &Scene, events: &[EventHit]) {
+    let settings = &scene.settings;
+
+    for hit in events {
+        let (x, y) = settings.ode_settings.projection().project(hit.t, &hit.y);
+        let (x, y) = point_to_screen(&settings.plot_settings, win, x, y);
+        let col = if hit.terminal { ORANGE } else { YELLOW };
+
+        draw.ellipse(pt2(x as f32, y as f32), 4.0, Style::stroke(col, 2.0));
+    }
+}
+
+/// Samples the solution at screen resolution over the part of it that is visible.
+fn sample_solution(
+    solution: &Solution,
+    settings: &Settings,
+    win: &Rect,
+) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let Some((t_start, t_end)) = solution.domain() else {
+        return Default::default();
+    };
+
+    let pixels = win.w().max(1.0) as usize;
+
+    match settings.ode_settings.projection() {
+        Projection::Graph => {
+            let plot_settings = &settings.plot_settings;
+            let t_start = t_start.max(plot_settings.x_min);
+            let t_end = t_end.min(plot_settings.x_max);
+
+            if t_start > t_end {
+                return Default::default();
+            }
+
+            let n = ((t_end - t_start) / (plot_settings.x_max - plot_settings.x_min)
+                * pixels as f64)
+                .ceil() as usize;
+
+            solution.sample((t_start, t_end), n.max(2))
+        }
+        // The curve is not a graph over the screen, so sample its whole domain at
+        // least as finely as the solver did.
+        Projection::Polar | Projection::Phase(..) => {
+            solution.sample((t_start, t_end), pixels.max(solution.len()))
+        }
+    }
+}
+
+fn compute_ode_soln(settings: &Settings) -> Result<Solution> {
+    solve_for_plot(&settings.ode_settings, &settings.plot_settings)
+}
+
+/// Solves the ODE from the initial condition set on the plot.
+fn solve_for_plot(ode_settings: &OdeSettings, plot_settings: &PlotSettings) -> Result<Solution> {
+    let (t_span, ics) = ode_settings.initial_value_problem();
+
+    let span = debug_span!(target: "metrics", "solve_ode");
+    let _enter = span.enter();
+    solve_ode(ode_settings, t_span, &ics, Some(plot_settings.viewport()))
+}
+
+/// Solves the ODE with each of the compared solvers, timing each of them.
+fn compute_comparisons(settings: &Settings) -> Vec<SolverComparison> {
+    settings
+        .plot_settings
+        .compared_solvers
+        .iter()
+        .map(|&solver| {
+            let mut ode_settings = settings.ode_settings.clone();
+            ode_settings.ode_solver = solver;
+
+            let start = Instant::now();
+            let solution = solve_for_plot(&ode_settings, &settings.plot_settings);
+
+            SolverComparison {
+                solver,
+                solution,
+                wall_time: start.elapsed(),
+            }
+        })
+        .collect()
+}
+
+/// The color of the `i`th compared solver.
+fn comparison_color(i: usize) -> Hsl {
+    hsl(0.1 + 0.17 * i as f32, 0.75, 0.6)
+}
+
+/// The relative energy drift (H - H₀) / |H₀| along a solution.
+fn relative_drift(settings: &OdeSettings, solution: &Solution) -> Result<(Vec<f64>, Vec<f64>)> {
+    let energy = energy(settings, solution)?;
+    let h0 = energy.first().copied().unwrap_or_default();
+    let scale = if h0.abs() > 1e-12 { h0.abs() } else { 1.0 };
+
+    let drift = energy.iter().map(|h| (h - h0) / scale).collect();
+    Ok((solution.t().to_vec(), drift))
+}
+
+/// Computes the energy drift of the solution, and of RK4 if it is compared against.
+fn compute_energy_drift(
+    settings: &Settings,
+    solution: &Solution,
+) -> Vec<(OdeSolver, Vec<f64>, Vec<f64>)> {
+    let ode_settings = &settings.ode_settings;
+    let rk4 = OdeSolver::Explicit(ExplicitMethod::RK4);
+
+    let mut drifts = vec![];
+    match relative_drift(ode_settings, solution) {
+        Ok((t, drift)) => drifts.push((ode_settings.ode_solver, t, drift)),
+        Err(e) => warn!("Failed to compute energy: {}", e),
+    }
+
+    if settings.plot_settings.compare_energy && ode_settings.ode_solver != rk4 {
+        let mut reference = ode_settings.clone();
+        reference.ode_solver = rk4;
+
+        let (t_span, ics) = reference.initial_value_problem();
+        let drift = solve_ode(&reference, t_span, &ics, None)
+            .and_then(|solution| relative_drift(&reference, &solution));
+
+        match drift {
+            Ok((t, drift)) => drifts.push((rk4, t, drift)),
+            Err(e) => warn!("Failed to compute reference energy: {}", e),
+        }
+    }
+
+    drifts
+}
+
+fn draw_energy_panel(draw: &impl Canvas, win: &Rect, drifts: &[(OdeSolver, Vec<f64>, Vec<f64>)]) {
+    if drifts.is_empty() {
+        return;
+    }
+
+    let rect = Rect::from_w_h(win.w() * 0.35, win.h() * 0.25).bottom_right_of(win.pad(20.0));
+
+    let (ts, ds): (Vec<f64>, Vec<f64>) = drifts
+        .iter()
+        .flat_map(|(_, t, d)| t.iter().copied().zip(d.iter().copied()))
+        .unzip();
+
+    let axes = AxesBuilder::new()
+        .fit_data(&ts, &ds)
+        .set_axis_location(AxisLocation::Y(YAxisLocation::Left))
+        .build();
+
+    axes.draw(draw, &rect, "Relative energy drift");
+
+    let colors = [
+        srgb(31.0 / 255.0, 101.0 / 255.0, 245.0 / 255.0),
+        srgb(0.6, 0.6, 0.6),
+    ];
+    for (i, ((solver, t, drift), color)) in drifts.iter().zip(colors).enumerate() {
+        axes.draw_series(draw, &rect, t, drift, color);
+
+        let label = Rect::from_x_y_w_h(
+            rect.right() - 70.0,
+            rect.top() - 25.0 - 14.0 * i as f32,
+            120.0,
+            14.0,
+        );
+        draw.text(solver.name(), label, 11, Justify::Right, color);
+    }
+}
+
+/// Fits the closed-form solution to the initial condition and compares it against the numerical one.
+fn compute_closed_form(scene: &mut Scene) -> Result<Option<ClosedFormComparison>> {
+    let settings = &scene.settings;
+    let ode_settings = &settings.ode_settings;
+
+    if !settings.plot_settings.closed_form
+        || ode_settings.inputs.mode != InputMode::Scalar
+        || ode_settings.coordinate != OdeCoordinate::Cartesian
+    {
+        return Ok(None);
+    }
+
+    // Deriving the general solution is symbolic, so only redo it when the ODE changes.
+    let input = &ode_settings.inputs.inputs[0];
+    if !matches!(&scene.general_solution, Some((cached, _)) if cached == input) {
+        let general = GeneralSolution::find(ode_settings).map_err(|e| e.to_string());
+        scene.general_solution = Some((input.clone(), general));
+    }
+
+    let general = match &scene.general_solution {
+        Some((_, Ok(Some(general)))) => general,
+        Some((_, Err(e))) => return Err(anyhow!("{}", e)),
+        _ => return Ok(None),
+    };
+    let Ok(solution) = &scene.solution else {
+        return Ok(None);
+    };
+
+    let mut closed_form = general.fit(ode_settings.ics[0], ode_settings.ics[1])?;
+
+    let (domain, image) = solution.resample(500);
+    let curve = domain
+        .iter()
+        .zip(&image)
+        .filter_map(|(&x, y)| Some((x, closed_form.eval(x, y[0])?)))
+        .collect();
+
+    let error = solution
+        .points()
+        .filter_map(|(x, y)| Some((x, (y[0] - closed_form.eval(x, y[0])?).abs())))
+        .unzip();
+
+    Ok(Some(ClosedFormComparison {
+        closed_form,
+        curve,
+        error,
+    }))
+}
+
+fn draw_closed_form(draw: &impl Canvas, win: &Rect, settings: &Settings, curve: &[(f64, f64)]) {
+    let vertices = curve.iter().map(|&(x, y)| {
+        let (x, y) = point_to_screen(&settings.plot_settings, win, x, y);
+        pt2(x as f32, y as f32)
+    });
+
+    draw.polyline_colored(&vertices.collect::<Vec<_>>(), 1.5, GREEN);
+}
+
+fn draw_error_panel(draw: &impl Canvas, win: &Rect, (xs, errors): &(Vec<f64>, Vec<f64>)) {
+    if xs.is_empty() {
+        return;
+    }
+
+    let rect = Rect::from_w_h(win.w() * 0.35, win.h() * 0.25).bottom_left_of(win.pad(20.0));
+
+    let axes = AxesBuilder::new()
+        .fit_data(xs, errors)
+        .set_axis_location(AxisLocation::Y(YAxisLocation::Left))
+        .build();
+
+    axes.draw(draw, &rect, "Pointwise error |y - y_exact|");
+    axes.draw_series(draw, &rect, xs, errors, GREEN);
+}
+
+/// Runs a convergence study against the closed-form solution if there is one,
+/// or else against a tight-tolerance numerical solution.
+fn run_convergence_study(scene: &Scene) -> Result<ConvergenceStudy> {
+    let ode_settings = &scene.settings.ode_settings;
+    let convergence = &scene.convergence.settings;
+
+    let reference = match &scene.closed_form {
+        Ok(Some(comparison)) => Reference::ClosedForm(comparison.closed_form.clone()),
+        _ => Reference::numerical(ode_settings, convergence)?,
+    };
+
+    ConvergenceStudy::run(ode_settings, convergence, reference)
+}
+
+/// Plots log₁₀ of the global error of each solver against log₁₀ of the step size.
+fn draw_convergence_panel(draw: &impl Canvas, win: &Rect, study: &ConvergenceStudy) {
+    let log_points = |series: &ConvergenceSeries| {
+        series
+            .step_sizes
+            .iter()
+            .zip(&series.errors)
+            .filter(|(_, &error)| error > 0.0)
+            .map(|(dt, error)| (dt.log10(), error.log10()))
+            .unzip::<_, _, Vec<_>, Vec<_>>()
+    };
+
+    let series = study
+        .series
+        .iter()
+        .map(|series| (series, log_points(series)))
+        .collect::<Vec<_>>();
+
+    let (xs, ys): (Vec<f64>, Vec<f64>) = series
+        .iter()
+        .flat_map(|(_, (xs, ys))| xs.iter().copied().zip(ys.iter().copied()))
+        .unzip();
+
+    if xs.is_empty() {
+        return;
+    }
+
+    let rect = Rect::from_w_h(win.w() * 0.35, win.h() * 0.35).top_right_of(win.pad(20.0));
+
+    let axes = AxesBuilder::new()
+        .fit_data(&xs, &ys)
+        .set_axis_location(AxisLocation::Y(YAxisLocation::Left))
+        .build();
+
+    axes.draw(draw, &rect, "log₁₀ global error vs log₁₀ step size");
+
+    for (i, (series, (xs, ys))) in series.iter().enumerate() {
+        let color = hsl(i as f32 / study.series.len() as f32, 0.7, 0.6);
+        axes.draw_series(draw, &rect, xs, ys, color);
+
+        let order = series
+            .observed_order
+            .map_or("-".to_string(), |order| format!("{:.2}", order));
+
+        let label = Rect::from_x_y_w_h(
+            rect.left() + 90.0,
+            rect.top() - 25.0 - 12.0 * i as f32,
+            140.0,
+            12.0,
+        );
+        let text = format!("{} ({})", series.solver.name(), order);
+        draw.text(&text, label, 10, Justify::Left, color);
+    }
+}
+
+fn point_to_screen(plot_settings: &PlotSettings, win: &Rect, x: f64, y: f64) -> (f64, f64) {
+    let x = map_range(
+        x,
+        plot_settings.x_min,
+        plot_settings.x_max,
+        win.left().into(),
+        win.right().into(),
+    );
+    let y = map_range(
+        y,
+        plot_settings.y_min,
+        plot_settings.y_max,
+        win.bottom().into(),
+        win.top().into(),
+    );
+    (x, y)
+}
+
+fn screen_to_point(plot_settings: &PlotSettings, win: &Rect, x: f64, y: f64) -> (f64, f64) {
+    let x = map_range(
+        x,
+        win.left().into(),
+        win.right().into(),
+        plot_settings.x_min,
+        plot_settings.x_max,
+    );
+    let y = map_range(
+        y,
+        win.bottom().into(),
+        win.top().into(),
+        plot_settings.y_min,
+        plot_settings.y_max,
+    );
+    (x, y)
+}
+
+fn raw_window_event(app: &App, model: &mut Model, event: &WindowEvent) {
+    // Let egui handle things like keyboard and mouse input.
+    model.egui.handle_raw_event(event);
+
+    // Keys typed into text fields, including the command palette, are not shortcuts.
+    if model.egui.ctx().wants_keyboard_input() {
+        return;
+    }
+
+    if let WindowEvent::KeyboardInput {
+        input:
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(key),
+                ..
+            },
+        ..
+    } = event
+    {
+        if let Some(action) = model.keybindings.action(*key, app.keys.mods) {
+            run_action(model, action);
+        }
+    }
+}
+
+/// Runs a command bound to a key or picked from the command palette.
+fn run_action(model: &mut Model, action: Action) {
+    let settings = &mut model.scene.settings;
+
+    match action {
+        Action::CommandPalette => model.palette.show(""),
+        Action::Undo => model.history.undo(settings, 1),
+        Action::Redo => model.history.redo(settings, 1),
+        Action::ResetView => settings.plot_settings.set_viewport(&model.home_view),
+        Action::ToggleFlow => {
+            let particles = &mut settings.plot_settings.particles;
+            particles.enabled = !particles.enabled;
+        }
+        Action::ClearTrajectories => {
+            settings.plot_settings.compared_solvers.clear();
+            model.scene.particles = Particles::default();
+        }
+        Action::CycleSolver => {
+            let solvers = OdeSolver::ALL;
+            let current = &mut settings.ode_settings.ode_solver;
+            let i = solvers
+                .iter()
+                .position(|s| *s == *current)
+                .map_or(0, |i| i + 1);
+            *current = solvers[i % solvers.len()];
+        }
+        Action::ExportPng => model.export.requested = Some(ExportFormat::Png),
+        Action::ExportSvg => model.export.requested = Some(ExportFormat::Svg),
+        Action::SaveSession => model.session.requested = Some(SessionAction::Save),
+        Action::OpenSession => model.session.requested = Some(SessionAction::Open),
+        Action::OpenPreset => model.palette.show("Preset: "),
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let draw = app.draw();
+
+    draw_scene(&draw, app.window_rect(), &model.scene);
+
+    // The inspector follows the pointer, so it is only drawn on screen.
+    if let Some((t, y)) = &model.hover {
+        let (win, _) = layout(app.window_rect(), &model.scene.settings);
+        draw_inspector(&draw, &win, &model.scene.settings, *t, y);
+    }
+
+    draw.to_frame(app, &frame)
+        .unwrap_or_else(|e| error!("Error drawing frame: {:?}", e));
+
+    model
+        .egui
+        .draw_to_frame(&frame)
+        .unwrap_or_else(|e| error!("Error drawing egui: {}", e));
+}
+
+/// The solution and those of the compared solvers, as tables to export.
+fn solution_tables(scene: &Scene, export: &ExportSettings) -> Result<Vec<DataTable>> {
+    let ode_settings = &scene.settings.ode_settings;
+
+    let solution = scene
+        .solution
+        .as_ref()
+        .map_err(|e| anyhow!("Failed to solve ODE: {}", e))?;
+    let mut tables = vec![DataTable::new(
+        ode_settings,
+        ode_settings.ode_solver,
+        solution,
+        export,
+    )?];
+
+    for comparison in &scene.comparisons {
+        if let Ok(solution) = &comparison.solution {
+            tables.push(DataTable::new(
+                ode_settings,
+                comparison.solver,
+                solution,
+                export,
+            )?);
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Solves the problem and writes each of `outputs`, in the format of its
+/// extension, without opening a window.
+fn render(settings: Settings, outputs: &[PathBuf], size: (u32, u32)) -> Result<()> {
+    let scene = Scene::new(settings);
+
+    for path in outputs {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let written = match (extension.as_str(), DataFormat::from_path(path)) {
+            ("svg", _) => export_svg(&scene, path, size).map(|_| vec![path.clone()]),
+            ("png", _) => export_png(&scene, path, size).map(|_| vec![path.clone()]),
+            (_, Some(format)) => solution_tables(&scene, &ExportSettings::default())
+                .and_then(|tables| write_data(path, format, &tables)),
+            _ => Err(anyhow!(
+                "Unknown output format of {}, expected .png, .svg, .csv, .json or .npy",
+                path.display()
+            )),
+        }?;
+
+        for path in written {
+            info!("Wrote {}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Solves the ODE over its whole span, regardless of the plot, and writes the
+/// steps of the solver or `samples` evenly spaced points to `out`.
+fn print_solution(
+    out: &mut impl Write,
+    ode_settings: &OdeSettings,
+    format: TableFormat,
+    samples: Option<usize>,
+) -> Result<()> {
+    let (t_span, ics) = ode_settings.initial_value_problem();
+    let solution = solve_ode(ode_settings, t_span, &ics, None)?;
+
+    let solver = ode_settings.ode_solver;
+    let table = match samples {
+        Some(n) => DataTable::resampled(ode_settings, solver, &solution, n),
+        None => {
+            let state_only = ExportSettings {
+                derivatives: false,
+                energy: false,
+            };
+            DataTable::new(ode_settings, solver, &solution, &state_only)?
+        }
+    };
+
+    let written = match format {
+        TableFormat::Csv => write!(out, "{}", table.to_csv()),
+        TableFormat::Json => writeln!(
+            out,
+            "{}",
+            serde_json::to_string_pretty(&table)
+                .map_err(|e| anyhow!("Failed to serialize solution: {}", e))?
+        ),
+    };
+
+    written.map_err(|e| anyhow!("Failed to write solution: {}", e))
+}
+
+/// Saves the scene as a PNG of `width` × `height` pixels.
+///
+/// The image is rasterized from the SVG export, so that the window and the
+/// command line write the same pixels for the same scene.
+fn export_png(scene: &Scene, path: &Path, size: (u32, u32)) -> Result<()> {
+    svg_to_png(&scene_svg(scene, size), path)
+}
+
+fn export_svg(scene: &Scene, path: &Path, size: (u32, u32)) -> Result<()> {
+    std::fs::write(path, scene_svg(scene, size))
+        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+}
+
+/// The scene drawn as an SVG image of `width` × `height` pixels.
+fn scene_svg(scene: &Scene, (width, height): (u32, u32)) -> String {
+    let bounds = Rect::from_w_h(width as f32, height as f32);
+    let svg = SvgCanvas::new(bounds);
+    draw_scene(&svg, bounds, scene);
+
+    svg.finish()
+}
+
+/// Draws the plots into `bounds`, without the egui overlays.
+fn draw_scene(draw: &impl Canvas, bounds: Rect, scene: &Scene) {
+    let settings = &scene.settings;
+
+    // The main plot and its insets take the bounds, less the column of time series.
+    let (win, subplots) = layout(bounds, settings);
+
+    draw.rect(bounds, Style::fill(BLACK));
+
+    if settings.plot_settings.particles.enabled {
+        scene.particles.draw(draw, |x, y| {
+            let (x, y) = point_to_screen(&settings.plot_settings, &win, x, y);
+            pt2(x as f32, y as f32)
+        });
+    }
 
     {
         let span = debug_span!(target: "metrics","draw_plot");
         let _enter = span.enter();
 
-        debug!(target: "metrics", "Computing ODE solution");
-        let ode_soln = compute_ode_soln(&settings.ode_settings);
-
-        match ode_soln {
-            Ok((domain, image)) => {
+        match &scene.solution {
+            Ok(solution) => {
                 debug!("Drawing ODE solution");
 
-                let image = image.into_iter().flatten().collect::<Vec<_>>();
-                draw_plot(&draw, &win, model, &domain, image.as_slice())
+                let (domain, image) = sample_solution(solution, settings, &win);
+                let coloring = &settings.plot_settings.coloring;
+                let evaluator = scene.coloring.as_ref().map(|(_, evaluator)| evaluator);
+                let values = coloring
+                    .values(evaluator, &domain, &image)
+                    .unwrap_or_else(|e| {
+                        debug!("Failed to color the trajectory: {}", e);
+                        None
+                    });
+
+                let range = values.as_deref().and_then(value_range);
+                let colors = match (&values, range) {
+                    (Some(values), Some((min, max))) => values
+                        .iter()
+                        .map(|v| {
+                            let s = if max > min {
+                                (v - min) / (max - min)
+                            } else {
+                                0.5
+                            };
+                            coloring.colormap.sample(s as f32).into_lin_srgba()
+                        })
+                        .collect(),
+                    _ => vec![main_color(); domain.len()],
+                };
+
+                draw_plot(draw, &win, scene, &domain, &image, &colors)
                     .unwrap_or_else(|e| error!("Error drawing plot: {}", e));
+
+                if let (Some(range), true) = (range, values.is_some()) {
+                    draw_colorbar(draw, &win, &settings.plot_settings, range);
+                }
+
+                draw_events(draw, &win, scene, &solution.events);
+
+                if settings.plot_settings.show_steps {
+                    draw_steps(draw, &win, settings, solution);
+                }
+                if settings.plot_settings.step_diagnostics {
+                    draw_step_diagnostics(draw, &win, solution);
+                }
             }
             Err(e) => {
                 error!("Failed to solve ODE: {}", e);
@@ -380,23 +2408,259 @@ fn view(app: &App, model: &Model, frame: Frame) {
         }
     }
 
-    draw_ic(&draw, &win, &settings.ode_settings);
+    draw_comparisons(draw, &win, scene);
+    draw_time_series(draw, &subplots, scene);
+    draw_time_cursor(draw, &win, scene);
+    draw_ic(draw, &win, settings);
+    draw_energy_panel(draw, &win, &scene.energy_drift);
 
-    draw.to_frame(app, &frame)
-        .unwrap_or_else(|e| error!("Error drawing frame: {:?}", e));
+    if let Ok(Some(comparison)) = &scene.closed_form {
+        draw_closed_form(draw, &win, settings, &comparison.curve);
+        draw_error_panel(draw, &win, &comparison.error);
+    }
 
-    model
-        .egui
-        .draw_to_frame(&frame)
-        .unwrap_or_else(|e| error!("Error drawing egui: {}", e));
+    if let Some(Ok(study)) = &scene.convergence.study {
+        draw_convergence_panel(draw, &win, study);
+    }
+}
+
+/// Marks the state at the time cursor on every trajectory.
+fn draw_time_cursor(draw: &impl Canvas, win: &Rect, scene: &Scene) {
+    let settings = &scene.settings;
+    let Some(t) = settings.plot_settings.time_cursor else {
+        return;
+    };
+
+    let main = main_color();
+    let solutions =
+        std::iter::once((main, &scene.solution)).chain(
+            scene.comparisons.iter().enumerate().map(|(i, comparison)| {
+                (comparison_color(i).into_lin_srgba(), &comparison.solution)
+            }),
+        );
+
+    let projection = settings.ode_settings.projection();
+    for (color, solution) in solutions {
+        let Some(y) = solution.as_ref().ok().and_then(|solution| solution.eval(t)) else {
+            continue;
+        };
+
+        let (x, y) = projection.project(t, &y);
+        let (x, y) = point_to_screen(&settings.plot_settings, win, x, y);
+
+        let style = Style::fill(color).with_stroke(WHITE, 1.5);
+        draw.ellipse(pt2(x as f32, y as f32), 5.0, style);
+    }
+}
+
+/// Shows the independent variable and every state component of a point of the solution.
+fn draw_inspector(draw: &impl Canvas, win: &Rect, settings: &Settings, t: f64, y: &[f64]) {
+    let ode_settings = &settings.ode_settings;
+
+    let (x_point, y_point) = ode_settings.projection().project(t, y);
+    let (x_point, y_point) = point_to_screen(&settings.plot_settings, win, x_point, y_point);
+    let point = pt2(x_point as f32, y_point as f32);
+
+    let lines = std::iter::once(format!("{} = {:.6}", ode_settings.independent_name(), t))
+        .chain(
+            ode_settings
+                .state_names()
+                .iter()
+                .zip(y)
+                .map(|(name, v)| format!("{} = {:.6}", name, v)),
+        )
+        .collect::<Vec<_>>();
+
+    let line_height = 14.0;
+    let size = (150.0, line_height * lines.len() as f32 + 8.0);
+
+    // Keep the tooltip on screen by flipping it to the other side of the point near the edges.
+    let dx = if point.x + size.0 + 12.0 > win.right() {
+        -size.0 / 2.0 - 12.0
+    } else {
+        size.0 / 2.0 + 12.0
+    };
+    let dy = if point.y - size.1 - 12.0 < win.bottom() {
+        size.1 / 2.0 + 12.0
+    } else {
+        -size.1 / 2.0 - 12.0
+    };
+    let center = point + pt2(dx, dy);
+
+    draw.ellipse(point, 4.0, Style::stroke(WHITE, 1.5));
+
+    let background = Style::fill(srgba(0.1, 0.1, 0.1, 0.9)).with_stroke(srgb(0.5, 0.5, 0.5), 1.0);
+    draw.rect(Rect::from_xy_wh(center, pt2(size.0, size.1)), background);
+
+    for (k, line) in lines.iter().enumerate() {
+        let y = center.y + size.1 / 2.0 - 4.0 - line_height * (k as f32 + 0.5);
+        let rect = Rect::from_x_y_w_h(center.x, y, size.0 - 10.0, line_height);
+        draw.text(line, rect, 11, Justify::Left, WHITE);
+    }
+}
+
+/// The state components of the time-series subplots, if the ODE is a system.
+fn time_series_components(settings: &Settings) -> Vec<usize> {
+    let ode_settings = &settings.ode_settings;
+    if !ode_settings.is_system() {
+        return vec![];
+    }
+
+    let dimensions = ode_settings.dimensions as usize;
+    settings
+        .plot_settings
+        .time_series
+        .iter()
+        .copied()
+        .filter(|&i| i < dimensions)
+        .collect()
+}
+
+/// Splits the window into the main plot and a column with one rect per time-series subplot.
+fn layout(win: Rect, settings: &Settings) -> (Rect, Vec<Rect>) {
+    let n = time_series_components(settings).len();
+    if n == 0 {
+        return (win, vec![]);
+    }
+
+    let plot = Rect::from_w_h(win.w() * 0.6, win.h()).top_left_of(win);
+    let column = Rect::from_w_h(win.w() * 0.4, win.h()).top_right_of(win);
+    let h = column.h() / n as f32;
+
+    let subplots = (0..n)
+        .map(|k| {
+            Rect::from_w_h(column.w(), h)
+                .top_left_of(column)
+                .shift_y(-h * k as f32)
+                .pad(15.0)
+        })
+        .collect();
+
+    (plot, subplots)
+}
+
+/// Plots the selected state components against time, in the colors of the main plot.
+fn draw_time_series(draw: &impl Canvas, subplots: &[Rect], scene: &Scene) {
+    let settings = &scene.settings;
+    let names = settings.ode_settings.state_names();
+
+    let main = main_color();
+    let solutions =
+        std::iter::once((main, &scene.solution)).chain(
+            scene.comparisons.iter().enumerate().map(|(i, comparison)| {
+                (comparison_color(i).into_lin_srgba(), &comparison.solution)
+            }),
+        );
+    let samples = solutions
+        .filter_map(|(color, solution)| {
+            let solution = solution.as_ref().ok()?;
+            let n = subplots
+                .first()
+                .map_or(2, |rect| rect.w().max(2.0) as usize);
+            Some((color, solution.resample(n)))
+        })
+        .collect::<Vec<_>>();
+
+    for (rect, i) in subplots.iter().zip(time_series_components(settings)) {
+        let series = samples
+            .iter()
+            .map(|(color, (t, y))| (*color, t, y.iter().map(|y| y[i]).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        let (ts, ys): (Vec<f64>, Vec<f64>) = series
+            .iter()
+            .flat_map(|(_, t, y)| t.iter().copied().zip(y.iter().copied()))
+            .unzip();
+
+        let axes = AxesBuilder::new()
+            .fit_data(&ts, &ys)
+            .set_axis_location(AxisLocation::Y(YAxisLocation::Left))
+            .build();
+
+        axes.draw(draw, rect, &format!("{} vs t", names[i]));
+        for (color, t, y) in &series {
+            axes.draw_series(draw, rect, t, y, *color);
+        }
+
+        if let Some(t) = settings.plot_settings.time_cursor {
+            let (y_min, y_max) = axes.y_limits();
+            let (start, end) = (
+                axes.to_screen(rect, t, y_min),
+                axes.to_screen(rect, t, y_max),
+            );
+            draw.line(start, end, 1.0, WHITE);
+        }
+    }
+}
+
+fn draw_ic(draw: &impl Canvas, win: &Rect, settings: &Settings) {
+    let ode_settings = &settings.ode_settings;
+
+    let (x0, y0) = match ode_settings.projection() {
+        Projection::Phase(i, j) => (ode_settings.ics[i], ode_settings.ics[j]),
+        Projection::Graph | Projection::Polar => (ode_settings.ics[0], ode_settings.ics[1]),
+    };
+    let (x, y) = point_to_screen(&settings.plot_settings, win, x0, y0);
+
+    draw.ellipse(pt2(x as f32, y as f32), 5.0, Style::fill(RED));
 }
 
-fn draw_ic(draw: &Draw, win: &Rect, ode_settings: &OdeSettings) {
-    let (x0, y0) = (ode_settings.ics[0], ode_settings.ics[1]);
-    let (x, y) = point_to_screen(&PlotSettings::default(), win, x0, y0);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printed(ode_settings: &OdeSettings, format: TableFormat, samples: Option<usize>) -> String {
+        let mut out = vec![];
+        print_solution(&mut out, ode_settings, format, samples).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn prints_solution_as_csv() {
+        let ode_settings = OdeSettings::default();
+        let (t_span, ics) = ode_settings.initial_value_problem();
+        let steps = solve_ode(&ode_settings, t_span, &ics, None).unwrap().len();
 
-    draw.ellipse()
-        .x_y(x as f32, y as f32)
-        .radius(5.0)
-        .color(RED);
+        let csv = printed(&ode_settings, TableFormat::Csv, None);
+        assert_eq!(csv.lines().next(), Some("x,y"));
+        assert_eq!(csv.lines().count(), steps + 1);
+
+        let csv = printed(&ode_settings, TableFormat::Csv, Some(5));
+        assert_eq!(csv.lines().next(), Some("x,y"));
+        assert_eq!(csv.lines().count(), 6);
+    }
+
+    #[test]
+    fn printed_and_plotted_polar_solutions_start_together() {
+        let cli = Cli::parse_from(["dydx", "solve", "--polar", "-e", "1/r", "--ics", "3,4"]);
+        let settings = initial_settings(&cli).unwrap();
+
+        let plotted = solve_for_plot(&settings.ode_settings, &settings.plot_settings).unwrap();
+        let csv = printed(&settings.ode_settings, TableFormat::Csv, None);
+        let first_row = csv
+            .lines()
+            .nth(1)
+            .unwrap()
+            .split(',')
+            .map(|value| value.parse::<f64>().unwrap())
+            .collect::<Vec<_>>();
+
+        // The initial condition (3, 4) of the plot, in polar coordinates
+        let (r, theta) = (5.0, 4.0f64.atan2(3.0));
+        assert_eq!(first_row, [r, theta]);
+        assert_eq!((plotted.t()[0], plotted.y()[0][0]), (r, theta));
+    }
+
+    #[test]
+    fn prints_solution_as_json() {
+        let ode_settings = OdeSettings::default();
+        let json = printed(&ode_settings, TableFormat::Json, Some(5));
+        let table: Value = serde_json::from_str(&json).unwrap();
+
+        for field in ["solver", "termination", "stats", "columns", "rows"] {
+            assert!(table.get(field).is_some(), "Missing field {}", field);
+        }
+        assert_eq!(table["columns"], serde_json::json!(["x", "y"]));
+        assert_eq!(table["rows"].as_array().map(Vec::len), Some(5));
+    }
 }
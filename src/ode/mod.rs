@@ -1,11 +0,0 @@
-#![allow(unused_imports)]
-
-mod parameters;
-mod schemes;
-mod settings;
-mod solver;
-
-pub use parameters::*;
-pub use schemes::*;
-pub use settings::*;
-pub use solver::solve_ode;
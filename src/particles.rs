@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+
+use nannou::{
+    prelude::{srgba, IntoLinSrgba, Point2},
+    rand::random_range,
+};
+use serde::{Deserialize, Serialize};
+
+use dydx_core::{VectorField, Viewport};
+
+use crate::canvas::Canvas;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticleSettings {
+    pub enabled: bool,
+    pub count: usize,
+    /// How many units of the independent variable pass per second
+    pub speed: f64,
+    /// The number of past positions drawn behind each particle
+    pub trail_length: usize,
+    /// The average number of seconds a particle lives before it respawns
+    pub lifetime: f32,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            count: 500,
+            speed: 1.0,
+            trail_length: 20,
+            lifetime: 4.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Particle {
+    /// The current position first, followed by the past positions
+    trail: VecDeque<(f64, f64)>,
+    age: f32,
+    lifetime: f32,
+}
+
+impl Particle {
+    fn spawn(viewport: &Viewport, lifetime: f32) -> Self {
+        let position = (
+            random_range(viewport.x_min, viewport.x_max),
+            random_range(viewport.y_min, viewport.y_max),
+        );
+
+        // Randomized lifetimes keep the particles from respawning in waves, and
+        // move particles on from the fixed points they collect at.
+        Self {
+            trail: VecDeque::from([position]),
+            age: 0.0,
+            lifetime: random_range(0.5, 1.5) * lifetime,
+        }
+    }
+}
+
+/// Particles advected by the vector field of the ODE in real time.
+#[derive(Debug, Clone, Default)]
+pub struct Particles {
+    particles: Vec<Particle>,
+}
+
+impl Particles {
+    /// Advances every particle by `dt` seconds, respawning those that died or left the viewport.
+    pub fn update(
+        &mut self,
+        settings: &ParticleSettings,
+        field: &mut VectorField,
+        viewport: &Viewport,
+        dt: f32,
+    ) {
+        self.particles.truncate(settings.count);
+        while self.particles.len() < settings.count {
+            self.particles
+                .push(Particle::spawn(viewport, settings.lifetime));
+        }
+
+        let h = settings.speed * dt as f64;
+        let inside = |(x, y): (f64, f64)| {
+            (viewport.x_min..=viewport.x_max).contains(&x)
+                && (viewport.y_min..=viewport.y_max).contains(&y)
+        };
+
+        for particle in &mut self.particles {
+            particle.age += dt;
+
+            let (x, y) = particle.trail[0];
+            let next = midpoint_step(field, (x, y), h);
+
+            match next {
+                Some(next) if inside(next) && particle.age < particle.lifetime => {
+                    particle.trail.push_front(next);
+                    particle.trail.truncate(settings.trail_length.max(1));
+                }
+                _ => *particle = Particle::spawn(viewport, settings.lifetime),
+            }
+        }
+    }
+
+    /// Draws each particle's trail, fading towards its oldest position.
+    pub fn draw(&self, draw: &impl Canvas, to_screen: impl Fn(f64, f64) -> Point2) {
+        for particle in &self.particles {
+            let n = particle.trail.len();
+            if n < 2 {
+                continue;
+            }
+
+            // Fade particles in as they spawn and out as they die.
+            let life = (particle.age / particle.lifetime).clamp(0.0, 1.0);
+            let fade = (4.0 * life * (1.0 - life)).min(1.0);
+
+            let points = particle.trail.iter().enumerate().map(|(k, &(x, y))| {
+                let alpha = 0.8 * fade * (1.0 - k as f32 / n as f32);
+                (
+                    to_screen(x, y),
+                    srgba(0.9, 0.9, 1.0, alpha).into_lin_srgba(),
+                )
+            });
+
+            draw.polyline(&points.collect::<Vec<_>>(), 1.5);
+        }
+    }
+}
+
+/// Advances `(x, y)` along the field by `h` with the explicit midpoint method.
+fn midpoint_step(field: &mut VectorField, (x, y): (f64, f64), h: f64) -> Option<(f64, f64)> {
+    let (u, v) = field.velocity(x, y)?;
+    let (u, v) = field.velocity(x + 0.5 * h * u, y + 0.5 * h * v)?;
+
+    Some((x + h * u, y + h * v))
+}
@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use tracing::warn;
+
+use crate::config_dir;
+use crate::session::Session;
+
+/// The presets shipped with dydx, as session files keyed by their file stem.
+const BUILT_IN: [(&str, &str); 10] = [
+    ("logistic", include_str!("../presets/logistic.json")),
+    (
+        "lotka-volterra",
+        include_str!("../presets/lotka-volterra.json"),
+    ),
+    ("van-der-pol", include_str!("../presets/van-der-pol.json")),
+    ("duffing", include_str!("../presets/duffing.json")),
+    (
+        "damped-pendulum",
+        include_str!("../presets/damped-pendulum.json"),
+    ),
+    ("sir", include_str!("../presets/sir.json")),
+    ("lorenz", include_str!("../presets/lorenz.json")),
+    ("rossler", include_str!("../presets/rossler.json")),
+    (
+        "fitzhugh-nagumo",
+        include_str!("../presets/fitzhugh-nagumo.json"),
+    ),
+    ("brusselator", include_str!("../presets/brusselator.json")),
+];
+
+/// A named starting point, stored in the session format.
+#[derive(Debug, Clone)]
+pub struct Preset {
+    /// The file stem the preset is selected by on the command line
+    pub key: String,
+    pub session: Session,
+}
+
+impl Preset {
+    /// The title shown in the menu, falling back to the key.
+    pub fn title(&self) -> &str {
+        self.session.name.as_deref().unwrap_or(&self.key)
+    }
+}
+
+/// The directory of user presets, `$XDG_CONFIG_HOME/dydx/presets` or
+/// `~/.config/dydx/presets`.
+fn user_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("presets"))
+}
+
+/// The built-in presets followed by the user's, where a user preset replaces
+/// the built-in one of the same name.
+pub fn all() -> Vec<Preset> {
+    let mut presets: Vec<Preset> = BUILT_IN
+        .iter()
+        .map(|(key, json)| Preset {
+            key: key.to_string(),
+            session: Session::from_json(json).expect("Built-in presets are valid sessions"),
+        })
+        .collect();
+
+    for preset in user_presets() {
+        match presets.iter_mut().find(|p| p.key == preset.key) {
+            Some(built_in) => *built_in = preset,
+            None => presets.push(preset),
+        }
+    }
+
+    presets
+}
+
+/// Finds a preset by its key, ignoring case.
+pub fn find(key: &str) -> Result<Session> {
+    let presets = all();
+
+    presets
+        .iter()
+        .find(|p| p.key.eq_ignore_ascii_case(key))
+        .map(|p| p.session.clone())
+        .ok_or_else(|| {
+            let keys: Vec<_> = presets.iter().map(|p| p.key.as_str()).collect();
+            anyhow!(
+                "Unknown preset {}, expected one of {}",
+                key,
+                keys.join(", ")
+            )
+        })
+}
+
+/// The valid `.json` sessions in the user directory, sorted by key. Invalid
+/// files are skipped with a warning so that one bad file doesn't hide the rest.
+fn user_presets() -> Vec<Preset> {
+    let Some(dir) = user_dir() else {
+        return vec![];
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return vec![];
+    };
+
+    let mut presets: Vec<Preset> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let key = path.file_stem()?.to_string_lossy().into_owned();
+            match Session::load(&path) {
+                Ok(session) => Some(Preset { key, session }),
+                Err(e) => {
+                    warn!("Skipping preset: {}", e);
+                    None
+                }
+            }
+        })
+        .collect();
+    presets.sort_by(|a, b| a.key.cmp(&b.key));
+
+    presets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dydx_core::solve_ode;
+
+    #[test]
+    fn built_in_presets_solve() {
+        for (key, json) in BUILT_IN {
+            let session = Session::from_json(json)
+                .unwrap_or_else(|e| panic!("Preset {} failed to load: {}", key, e));
+            let ode_settings = session.into_settings().ode_settings;
+
+            let (t_span, ics) = ode_settings.initial_value_problem();
+            let solution = solve_ode(&ode_settings, t_span, &ics, None)
+                .unwrap_or_else(|e| panic!("Preset {} failed to solve: {}", key, e));
+            assert!(!solution.is_empty(), "Preset {} has no solution", key);
+        }
+    }
+}
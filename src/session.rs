@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use dydx_core::OdeSettings;
+
+use crate::{PlotSettings, Settings};
+
+/// The version of the session format written by this build.
+pub const SESSION_VERSION: u64 = 1;
+
+/// Upgrades a session by one version: the `i`th migration takes a session of
+/// version `i + 1` to version `i + 2`.
+///
+/// Fields that are added with a default need no migration, as missing fields
+/// are filled in with their defaults.
+const MIGRATIONS: &[fn(&mut Value)] = &[];
+
+/// The settings of the ODE and the plot, saved as a JSON project file so that
+/// a setup can be shared exactly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u64,
+    /// A title for presets and shared setups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub ode_settings: OdeSettings,
+    pub plot_settings: PlotSettings,
+}
+
+impl Session {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            version: SESSION_VERSION,
+            name: None,
+            description: None,
+            ode_settings: settings.ode_settings.clone(),
+            plot_settings: settings.plot_settings.clone(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| anyhow!("Failed to serialize session: {}", e))?;
+
+        std::fs::write(path, json).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Loads a session, migrating it from older versions of the format.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+
+        Self::from_json(&json).map_err(|e| anyhow!("Invalid session {}: {}", path.display(), e))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value =
+            serde_json::from_str(json).map_err(|e| anyhow!("Failed to parse JSON: {}", e))?;
+
+        let mut session: Session = serde_json::from_value(migrate(value)?)?;
+
+        // The symbols and parsed expressions are not saved, only their inputs.
+        session.ode_settings.rebuild();
+        session.ode_settings.validate()?;
+        session.plot_settings.validate()?;
+
+        Ok(session)
+    }
+
+    pub fn into_settings(self) -> Settings {
+        Settings {
+            ode_settings: self.ode_settings,
+            plot_settings: self.plot_settings,
+        }
+    }
+}
+
+/// Brings a session of any supported version up to `SESSION_VERSION`.
+fn migrate(mut session: Value) -> Result<Value> {
+    let version = session
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| anyhow!("The session has no format version"))?;
+
+    if version == 0 || version > SESSION_VERSION {
+        return Err(anyhow!(
+            "Unsupported session version {}, expected at most {}",
+            version,
+            SESSION_VERSION
+        ));
+    }
+
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(&mut session);
+    }
+    session["version"] = SESSION_VERSION.into();
+
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_json() -> Value {
+        let settings = Settings {
+            ode_settings: OdeSettings::default(),
+            plot_settings: PlotSettings::default(),
+        };
+        serde_json::to_value(Session::new(&settings)).unwrap()
+    }
+
+    fn load(value: &Value) -> Result<Session> {
+        Session::from_json(&value.to_string())
+    }
+
+    #[test]
+    fn loads_saved_session() {
+        assert!(load(&session_json()).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let invalid: [fn(&mut Value); 3] = [
+            |session| {
+                session["plot_settings"]["x_min"] = 5.0.into();
+                session["plot_settings"]["x_max"] = -5.0.into();
+            },
+            |session| session["ode_settings"]["integration_length"] = (-1.0).into(),
+            |session| {
+                session["ode_settings"]["parameters"]["min_step_size"] = 1.0.into();
+                session["ode_settings"]["parameters"]["max_step_size"] = 0.1.into();
+            },
+        ];
+
+        for edit in invalid {
+            let mut session = session_json();
+            edit(&mut session);
+            assert!(load(&session).is_err());
+        }
+    }
+}